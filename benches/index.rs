@@ -0,0 +1,102 @@
+#![feature(test)]
+
+extern crate test;
+extern crate satire;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+
+use test::Bencher;
+
+use satire::indexer::parser::{Path, Tagged};
+use satire::indexer::storage::IndexBuilder;
+
+const DIRS: usize = 100;
+const FILES_IN_DIR: usize = 100;
+
+/// Writes 10k files in 100 directories, every file defines a struct with a method
+/// and calls items of the next one.
+fn generate_tree() -> String {
+    let root = env::temp_dir().join("satire_bench");
+    let files = DIRS * FILES_IN_DIR;
+
+    for d in 0..DIRS {
+        let dir = root.join(format!("dir{}", d));
+        fs::create_dir_all(&dir).unwrap();
+
+        for f in 0..FILES_IN_DIR {
+            let i = d * FILES_IN_DIR + f;
+            let mut file = File::create(dir.join(format!("file{}.rs", f))).unwrap();
+            write!(file, "pub struct Item{i} {{
+    value: u32,
+}}
+
+impl Item{i} {{
+    pub fn new() -> Item{i} {{
+        Item{i} {{ value: {i} }}
+    }}
+}}
+
+pub fn run{i}() {{
+    helper{j}();
+    Item{j}::new();
+}}
+
+pub fn helper{i}() {{
+}}
+", i = i, j = (i + 1) % files).unwrap();
+        }
+    }
+
+    format!("{}/", root.to_str().unwrap())
+}
+
+/// Index of the generated tree, the tree is removed after indexing.
+fn build() -> IndexBuilder {
+    let root = generate_tree();
+    let mut index_builder = IndexBuilder::new();
+    index_builder.build_dir(&root).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+    index_builder
+}
+
+/// References of the calls in the first 100 files.
+fn calls(index_builder: &IndexBuilder) -> Vec<Path> {
+    let mut calls = vec![];
+    for parsed_file in index_builder.set.iter().take(100) {
+        for &(ref tagged, _) in &parsed_file.preparsed.parsed {
+            if let &Tagged::Calling(ref use_context) = tagged {
+                calls.push(use_context.reference.clone());
+            }
+        }
+    }
+    calls
+}
+
+#[bench]
+fn find_linear(b: &mut Bencher) {
+    let index_builder = build();
+    let calls = calls(&index_builder);
+
+    b.iter(|| {
+        for path in &calls {
+            for parsed_file in &index_builder.set {
+                test::black_box(parsed_file.preparsed.find(path));
+            }
+        }
+    });
+}
+
+#[bench]
+fn find_symbol_table(b: &mut Bencher) {
+    let index_builder = build();
+    let calls = calls(&index_builder);
+    let index = index_builder.index();
+
+    b.iter(|| {
+        for path in &calls {
+            test::black_box(index.find(path));
+        }
+    });
+}
@@ -1,63 +1,502 @@
 use std::io::prelude::*;
 use std::io::BufWriter;
 use std::fs::File;
-use std::rc::Rc;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-use indexer::parser::Tagged;
-use indexer::lexer::Span;
+use indexer::parser::{Tagged, Path, AttrContext};
+use indexer::lexer::{Span, bom_len};
 use indexer::lexer::WhitespaceType;
 use indexer::storage::{FileSource, Info};
+use indexer::tree::TreeDir;
+use indexer::symbols::Symbol;
+use indexer::outline::OutlineItem;
+
+/// Where sources are read from and pages are written to. Files of the index
+/// are relative to `source_root`, their pages keep the same paths in `output_root`.
+#[derive(Debug, Clone)]
+pub struct GenConfig {
+    pub source_root: String,
+    pub output_root: String,
+    /// URL of `output_root` for absolute links, e.g. `https://example.com/code/`.
+    /// Links are relative to the current page without it, so the site works from any sub-path and `file://`
+    pub base_url: Option<String>,
+    /// Page template with `{{title}}`, `{{tree}}`, `{{content}}` and `{{outline}}` placeholders,
+    /// `{{root}}` is the URL of `output_root` from the page (static assets are in `{{root}}static/`).
+    /// Older templates with `<pre><code>{{code}}</code></pre>` instead of `{{content}}` still work
+    pub template: String,
+    /// Scripts and styles, copied into `output_root` as `static/`
+    pub static_dir: String,
+    /// Operators are wrapped in `<span class='op'>` for highlighting
+    pub operators: bool,
+}
+
+/// Links from the page of one file.
+pub struct Links {
+    /// URL of the output root, ends with the slash unless it's empty
+    pub root: String,
+}
+
+impl Links {
+    pub fn page_url(&self, file: &str) -> String {
+        format!("{}{}.html", self.root, file)
+    }
+
+    /// URL of the identifier at the source.
+    pub fn source_url(&self, source: &FileSource) -> String {
+        format!("{}#{}", self.page_url(&source.file), ident_anchor(source.line, source.col))
+    }
+
+    /// `dir` is empty or ends with the slash.
+    pub fn dir_url(&self, dir: &str) -> String {
+        format!("{}{}index.html", self.root, dir)
+    }
+}
+
+impl GenConfig {
+    pub fn new(source_root: &str, output_root: &str) -> GenConfig {
+        GenConfig {
+            source_root: dir_path(source_root),
+            output_root: dir_path(output_root),
+            base_url: None,
+            template: "web/code_template.html".to_string(),
+            static_dir: "web/static/".to_string(),
+            operators: false,
+        }
+    }
+
+    pub fn page_file(&self, file: &str) -> PathBuf {
+        PathBuf::from(format!("{}{}.html", self.output_root, file))
+    }
+
+    /// Page of the directory, `dir` is empty or ends with the slash.
+    pub fn dir_page_file(&self, dir: &str) -> PathBuf {
+        PathBuf::from(format!("{}{}index.html", self.output_root, dir))
+    }
+
+    /// Links from the page of `file` (or of the directory with the path ending with the slash).
+    pub fn links(&self, file: &str) -> Links {
+        let root = match self.base_url {
+            Some(ref base_url) => dir_path(base_url),
+            None => file.matches('/').map(|_| "../").collect(),
+        };
+
+        Links {
+            root: root,
+        }
+    }
+
+    /// Path of the file from `source_root`.
+    pub fn relative<'a>(&self, file: &'a str) -> &'a str {
+        if file.starts_with(&self.source_root) {
+            &file[self.source_root.len()..]
+        } else {
+            file
+        }
+    }
+}
+
+/// Directories are kept with the trailing slash, so files are appended to them as strings.
+pub fn dir_path(dir: &str) -> String {
+    if dir.is_empty() || dir.ends_with('/') {
+        dir.to_string()
+    } else {
+        format!("{}/", dir)
+    }
+}
 
 impl FileSource {
-    pub fn render_html(&self, name: &str) -> String {
-        let file = format!("test/{}.html", self.file);
-        format!("<a href='/{}#l{}'>{}</a>", file, self.line, name)
+    pub fn render_html(&self, links: &Links, name: &str) -> String {
+        format!("<a href='{}'>{}</a>", links.source_url(self), name)
     }
 }
 
 pub fn escape_html(content: &str) -> String {
-    content.to_string().replace("<", "&lt;").replace(">", "&gt;")
+    content.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;")
+}
+
+/// Text for attribute values in either quotes.
+pub fn escape_attr(content: &str) -> String {
+    escape_html(content).replace("\"", "&quot;").replace("'", "&#39;")
+}
+
+/// First paragraph of the doc comment joined into one line.
+pub fn doc_summary(doc: &str) -> String {
+    doc.lines()
+        .map(|line| line.trim())
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Summary of the doc comment at the top of popovers.
+pub fn render_doc(doc: &Option<String>) -> String {
+    match *doc {
+        Some(ref doc) => format!("<p class='doc'>{}</p>", escape_html(&doc_summary(doc))),
+        None => String::new(),
+    }
+}
+
+pub fn render_refs(links: &Links, refs: &[FileSource]) -> String {
+    refs.iter().fold(String::new(), |res, i| {
+        res + &format!("<li><a href='{}' target='_blank'>{}: {}
+        </a></li>", links.source_url(i), i.file, i.line)
+    })
+}
+
+pub fn render_candidates(links: &Links, candidates: &[(Path, FileSource)]) -> String {
+    candidates.iter().fold(String::new(), |res, &(ref path, ref i)| {
+        res + &format!("<li><a href='{}' target='_blank'>{}</a> {}: {}</li>", links.source_url(i), path, i.file, i.line)
+    })
+}
+
+/// Usages grouped by file, `usages` are sorted.
+pub fn render_usages(links: &Links, usages: &[FileSource]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < usages.len() {
+        let file = &usages[i].file;
+        let mut lines = String::new();
+
+        while i < usages.len() && usages[i].file == *file {
+            lines.push_str(&format!(" <a href='{}' target='_blank'>{}</a>", links.source_url(&usages[i]), usages[i].line));
+            i += 1;
+        }
+
+        out.push_str(&format!("<li>{}:{}</li>", file, lines));
+    }
+
+    out
+}
+
+/// Sidebar with the whole source tree, the same for all pages: `links` are from the output root.
+/// Items have `data-path` attributes, so `static/js/tree.js` can expand the path to the current page.
+pub fn render_tree(links: &Links, root: &TreeDir) -> String {
+    format!("<ul class='tree'><li><a href='{}' data-path=''>{}/</a><ul>{}</ul></li></ul>",
+        links.dir_url(""), escape_html(&root.name), render_tree_items(links, root))
+}
+
+fn render_tree_items(links: &Links, dir: &TreeDir) -> String {
+    let mut out = String::new();
+
+    for sub in &dir.dirs {
+        out.push_str(&format!("<li><details><summary><a href='{}' data-path='{}'>{}/</a></summary><ul>{}</ul></details></li>",
+            links.dir_url(&sub.path), escape_attr(&sub.path), escape_html(&sub.name), render_tree_items(links, sub)));
+    }
+
+    for file in &dir.files {
+        if file.indexed {
+            out.push_str(&format!("<li><a href='{}' data-path='{}'>{}</a></li>",
+                links.page_url(&file.path), escape_attr(&file.path), escape_html(&file.name)));
+        } else {
+            out.push_str(&format!("<li><span data-path='{}'>{}</span></li>", escape_attr(&file.path), escape_html(&file.name)));
+        }
+    }
+
+    out
+}
+
+/// `tree-index.js` of the output root with the sidebar of `render_tree`.
+pub fn render_tree_index(links: &Links, root: &TreeDir) -> String {
+    format!("var SOURCE_TREE = {};\n", json_string(&render_tree(links, root)))
+}
+
+/// Replaces `{{tree}}` of the page of `current` (the path of the file or the directory),
+/// the sidebar is loaded from `tree-index.js` rather than repeated in every page.
+pub fn render_tree_container(links: &Links, current: &str) -> String {
+    format!("<div class='tree-container' id='tree' data-root='{}' data-current='{}'></div>\
+        <script src='{}tree-index.js'></script><script src='{}static/js/tree.js'></script>",
+        escape_attr(&links.root), escape_attr(current), escape_attr(&links.root), escape_attr(&links.root))
+}
+
+/// Table of subdirectories and files of the directory page.
+pub fn render_dir(links: &Links, dir: &TreeDir) -> String {
+    let mut rows = String::new();
+
+    if !dir.path.is_empty() {
+        let parent = match dir.path[..dir.path.len() - 1].rfind('/') {
+            Some(slash) => &dir.path[..slash + 1],
+            None => "",
+        };
+        rows.push_str(&format!("<tr><td><a href='{}'>..</a></td><td></td><td></td><td></td></tr>", links.dir_url(parent)));
+    }
+
+    for sub in &dir.dirs {
+        rows.push_str(&format!("<tr><td><a href='{}'>{}/</a></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            links.dir_url(&sub.path), escape_html(&sub.name), sub.size(), sub.lines(), sub.symbols()));
+    }
+
+    for file in &dir.files {
+        let name = if file.indexed {
+            format!("<a href='{}'>{}</a>", links.page_url(&file.path), escape_html(&file.name))
+        } else {
+            escape_html(&file.name)
+        };
+        rows.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>", name, file.size, file.lines, file.symbols));
+    }
+
+    format!("<table class='table table-condensed'><thead><tr><th>Name</th><th>Size</th><th>Lines</th><th>Symbols</th></tr></thead><tbody>{}</tbody></table>", rows)
+}
+
+/// Items of the file linking to their lines on the same page.
+pub fn render_outline(items: &[OutlineItem]) -> String {
+    if items.len() == 0 {
+        return String::new();
+    }
+
+    let mut out = String::from("<ul class='outline'>");
+    for item in items {
+        let title = match item.doc {
+            Some(ref doc) => format!(" title='{}'", escape_attr(&doc_summary(doc))),
+            None => String::new(),
+        };
+        out.push_str(&format!("<li><span class='kind'>{}</span> <a href='#{}'{}>{}</a>{}</li>",
+            item.kind, ident_anchor(item.line, item.col), title, escape_html(&item.name), render_outline(&item.children)));
+    }
+    out.push_str("</ul>");
+    out
+}
+
+/// JSON string literal.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `search-index.js` read by `static/js/search.js`: `SEARCH_INDEX` is an array of
+/// `[name, kind, path, file, line, col]` with paths from the crate root and files from the source root.
+/// It's a script rather than JSON, since browsers don't let `file://` pages request files.
+pub fn render_search_index(symbols: &[&Symbol]) -> String {
+    let entries: Vec<String> = symbols.iter().map(|symbol| {
+        format!("[{},{},{},{},{},{}]",
+            json_string(&symbol.path.name()), json_string(symbol.path.kind()),
+            json_string(&symbol.full_names().join("::")), json_string(&symbol.source.file), symbol.source.line, symbol.source.col)
+    }).collect();
+    format!("var SEARCH_INDEX = [{}];\n", entries.join(",\n"))
+}
+
+pub fn popover(title: &str, content: &str, text: &str) -> String {
+    let title_attr = if title.is_empty() {
+        String::new()
+    } else {
+        format!(" title='{}'", escape_attr(title))
+    };
+    // The content is HTML itself
+    format!("<a tabindex='0' role='button' data-container='body' data-trigger='focus' data-toggle='popover' data-placement='bottom'{} data-content=\"{}\">{}</a>", title_attr, escape_attr(content), text)
+}
+
+/// Escaped text starting at `line` with anchors of the lines after its newlines.
+pub fn escape_lines(text: &str, line: usize) -> String {
+    text.split('\n').enumerate().map(|(i, part)| {
+        if i == 0 { escape_html(part) } else { format!("{}{}", line_anchor(line + i), escape_html(part)) }
+    }).collect::<Vec<String>>().join("\n")
+}
+
+/// Attribute with derived traits linking to their definitions.
+pub fn render_attribute(links: &Links, content: &str, span: &Span, attribute: &AttrContext, candidates: &[(Path, FileSource)]) -> String {
+    let mut out = String::from("<span class='attribute'>");
+    let mut till = span.lo;
+    let mut line = span.line;
+
+    for &(ref path, ref derive) in &attribute.derives {
+        let sources: Vec<(Path, FileSource)> = candidates.iter().filter(|&&(ref candidate, _)| candidate == path).cloned().collect();
+        if sources.len() == 0 {
+            continue;
+        }
+
+        out.push_str(&escape_lines(&content[till..derive.lo], line));
+        line = derive.line;
+
+        let name = escape_html(&content[derive.lo..derive.hi]);
+        if sources.len() == 1 {
+            out.push_str(&sources[0].1.render_html(links, &name));
+        } else {
+            let title = format!("Candidates ({})", sources.len());
+            out.push_str(&popover(&title, &format!("<ul>{}</ul>", render_candidates(links, &sources)), &name));
+        }
+        till = derive.hi;
+    }
+
+    out.push_str(&escape_lines(&content[till..span.hi], line));
+    out.push_str("</span>");
+    out
+}
+
+/// CSS classes of the identifier by its role, definition sites also have `def`.
+pub fn role_class(tagged: &Tagged) -> Option<&'static str> {
+    fn kind_class(kind: &str) -> &'static str {
+        match kind {
+            "struct" | "enum" | "trait" | "type" => "type",
+            "const" | "static" => "constant",
+            "variant" => "variant",
+            "macro" => "macro",
+            "mod" => "module",
+            _ => "fn",
+        }
+    }
+
+    match tagged {
+        &Tagged::Definition(ref use_context) => Some(match kind_class(use_context.reference.kind()) {
+            "type" => "type def",
+            "constant" => "constant def",
+            "variant" => "variant def",
+            "macro" => "macro def",
+            "module" => "module def",
+            _ => "fn def",
+        }),
+        &Tagged::Calling(ref use_context) => Some(kind_class(use_context.reference.kind())),
+        &Tagged::TypeRef(_) => Some("type"),
+        &Tagged::Implementation(ref impl_context) if impl_context.is_inherent() => Some("type"),
+        &Tagged::MethodCall(_) => Some("method"),
+        &Tagged::MacroCall(_) => Some("macro"),
+        &Tagged::Field(_) => Some("field"),
+        &Tagged::Local(_) => Some("local"),
+        _ => None,
+    }
 }
 
-pub fn to_string(content: Rc<String>, items: &[(Tagged, Span, Option<Box<Info>>)]) -> String {
+/// Name of the anchor at the identifier, e.g. `l10c5`.
+pub fn ident_anchor(line: usize, col: usize) -> String {
+    format!("l{}c{}", line, col)
+}
+
+/// Start of the line, the number is shown by `main.css` from `data-line` so it isn't copied with the code.
+/// `static/js/lines.js` highlights lines and ranges like `#l10-l25`.
+pub fn line_anchor(line: usize) -> String {
+    format!("<a class=\"line\" name=\"l{0}\" href=\"#l{0}\" data-line=\"{0}\"></a>", line)
+}
+
+pub fn to_string(links: &Links, content: Arc<String>, items: &[(Tagged, Span, Option<Box<Info>>)]) -> String {
     let mut out = String::new();
 
-    let mut till = 0;
+    // The byte order mark isn't shown
+    let mut till = bom_len(&content);
     for &(ref tagged, ref span, ref info) in items {
         //println!("A: {}, {}, {}", till, span.lo, span.hi);
+        if span.lo < till {
+            // Inside an attribute
+            continue;
+        }
         out.push_str(&escape_html(&content[till..span.lo]));
 
-        let cnt = &escape_html(&content[span.lo..span.hi]);
+        let mut cnt = escape_lines(&content[span.lo..span.hi], span.line);
+        if let Some(class) = role_class(tagged) {
+            cnt = format!("<span class='{}'>{}</span>", class, cnt);
+        }
+        let cnt = &cnt;
         let fmt;
 
+        match tagged {
+            &Tagged::Definition(_) | &Tagged::Calling(_) | &Tagged::MethodCall(_) | &Tagged::MacroCall(_) |
+            &Tagged::TypeRef(_) | &Tagged::Implementation(_) => {
+                out.push_str(&format!("<a name=\"{}\"></a>", ident_anchor(span.line, span.col)));
+            },
+            _ => {},
+        }
+
         match tagged {
             &Tagged::Keyword(_) => {
                 fmt = format!("<b>{}</b>", &cnt)
             },
             &Tagged::Comment => {
-                fmt = format!("<span style='color: green;'>{}</span>", &cnt)
+                fmt = format!("<span class='comment'>{}</span>", &cnt)
+            },
+            &Tagged::DocComment => {
+                fmt = format!("<span class='doc-comment'>{}</span>", &cnt)
             },
             &Tagged::QuotedString => {
-                fmt = format!("<span style='color: green;'>{}</span>", &cnt)
+                fmt = format!("<span class='string'>{}</span>", &cnt)
+            },
+            &Tagged::Char => {
+                fmt = format!("<span class='char'>{}</span>", &cnt)
+            },
+            &Tagged::Number => {
+                fmt = format!("<span class='number'>{}</span>", &cnt)
+            },
+            &Tagged::Lifetime => {
+                fmt = format!("<span class='lifetime'>{}</span>", &cnt)
+            },
+            &Tagged::Operator => {
+                fmt = format!("<span class='op'>{}</span>", &cnt)
+            },
+            &Tagged::Attribute(ref attribute) => {
+                let candidates = match info {
+                    &Some(ref add_info) => &add_info.candidates[..],
+                    _ => &[],
+                };
+                fmt = render_attribute(links, &content, span, attribute, candidates);
             },
-            &Tagged::Calling(ref use_context) => {
+            &Tagged::Calling(_) | &Tagged::Import(_) | &Tagged::MacroCall(_) | &Tagged::TypeRef(_) => {
                 match info {
                     &Some(ref add_info) => {
-                        let refs = add_info.refs.iter().enumerate().fold(String::new(), |res, (_, i)| {
-                            let file = format!("test/{}.html", i.file);
-                            res + &format!("<li><a href='/{}#l{}' target='_blank'>{}: {}
-                            </a></li>", file, i.line, file, i.line)
-                        });
-                        fmt = format!("<a tabindex='0' role='button' data-container='body' data-trigger='focus' data-toggle='popover' data-placement='bottom' data-content=\"<ul>{}</ul>\">{}</a>", refs, use_context.reference.path[0].1);
+                        let refs = render_refs(links, &add_info.refs);
+                        fmt = popover("", &format!("{}<ul>{}</ul>", render_doc(&add_info.doc), refs), cnt);
                     },
                     _ => { fmt = cnt.to_string() },
                 }
             },
+            &Tagged::MethodCall(_) => {
+                match info {
+                    &Some(ref add_info) => {
+                        let title = format!("Candidates ({})", add_info.candidates.len());
+                        let candidates = render_candidates(links, &add_info.candidates);
+                        fmt = popover(&title, &format!("<ul>{}</ul>", candidates), cnt);
+                    },
+                    _ => { fmt = cnt.to_string() },
+                }
+            },
+            &Tagged::Definition(_) => {
+                match info {
+                    &Some(ref add_info) => {
+                        let mut titles = vec![];
+                        let mut content = render_doc(&add_info.doc);
+
+                        if add_info.impls.len() > 0 {
+                            titles.push(format!("Implementations ({})", add_info.impls.len()));
+                            content.push_str(&format!("<ul>{}</ul>", render_refs(links, &add_info.impls)));
+                        }
+                        if add_info.usages.len() > 0 {
+                            titles.push(format!("Usages ({})", add_info.usages.len()));
+                            content.push_str(&format!("<ul>{}</ul>", render_usages(links, &add_info.usages)));
+                        }
+
+                        fmt = popover(&titles.join(", "), &content, cnt);
+                    },
+                    _ => { fmt = cnt.to_string() },
+                }
+            },
+            &Tagged::Implementation(ref impl_context) => {
+                let title = if impl_context.is_inherent() {
+                    format!("Implementation of {}", impl_context.self_type.name())
+                } else {
+                    format!("Implements {}", impl_context.trait_ref.name())
+                };
+                let refs = match info {
+                    &Some(ref add_info) => render_refs(links, &add_info.refs),
+                    _ => String::new(),
+                };
+                fmt = popover(&title, &format!("<ul>{}</ul>", refs), cnt);
+            },
             &Tagged::Whitespace(WhitespaceType::Newline) => {
                 if span.line == 1 {
-                    fmt = format!("<a name=\"l{}\"></a>", span.line);
+                    fmt = line_anchor(span.line);
                 } else {
-                    fmt = format!("\n<a name=\"l{}\"></a>", span.line);
+                    fmt = format!("\n{}", line_anchor(span.line));
                 }
             },
             _ => {
@@ -75,9 +514,9 @@ pub fn to_string(content: Rc<String>, items: &[(Tagged, Span, Option<Box<Info>>)
     out
 }
 
-pub fn to_file(filename: String, content: Rc<String>, items: &[(Tagged, Span, Option<Box<Info>>)]) {
+pub fn to_file(links: &Links, filename: String, content: Arc<String>, items: &[(Tagged, Span, Option<Box<Info>>)]) {
     let output = File::create(filename).unwrap();
     let mut writer = BufWriter::new(output);
-    let out = to_string(content, items);
+    let out = to_string(links, content, items);
     writer.write(out.as_bytes()).unwrap();
 }
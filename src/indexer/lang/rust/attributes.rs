@@ -0,0 +1,120 @@
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+
+use indexer::lexer::{Token, Span};
+use indexer::parser::{Path, Tagged, AttrContext};
+use indexer::lang::rust::scope::ScopeTracker;
+
+/// Collects `#[...]` and `#![...]` attributes, names of `#[derive(...)]` are
+/// tagged as traits so the pages can link them.
+pub struct AttributeParser {
+    pub scope: Rc<RefCell<ScopeTracker>>,
+    /// `#` of the attribute being parsed
+    pub start: Option<Span>,
+    pub inner: bool,
+    /// Depth of square brackets, 0 before `[`
+    pub depth: usize,
+    pub tokens: Vec<(Token, Span)>,
+}
+
+impl AttributeParser {
+    pub fn new(scope: Rc<RefCell<ScopeTracker>>) -> AttributeParser {
+        AttributeParser {
+            scope: scope,
+            start: None,
+            inner: false,
+            depth: 0,
+            tokens: vec![],
+        }
+    }
+
+    /// Returns if the token belongs to an attribute and the tag of the attribute when its `]` is reached.
+    pub fn push(&mut self, token: &Token, span: &Span) -> (bool, Vec<(Tagged, Span)>) {
+        if self.start.is_none() {
+            if let Token::Pound = *token {
+                self.start = Some(span.clone());
+                return (true, vec![]);
+            }
+            return (false, vec![]);
+        }
+
+        if self.depth == 0 {
+            match *token {
+                Token::Bang if !self.inner => {
+                    self.inner = true;
+                    return (true, vec![]);
+                },
+                Token::LBracket => {
+                    self.depth = 1;
+                    return (true, vec![]);
+                },
+                _ => {
+                    // `#` of a macro
+                    self.reset();
+                    return (false, vec![]);
+                },
+            }
+        }
+
+        match *token {
+            Token::LBracket => { self.depth += 1; },
+            Token::RBracket => { self.depth -= 1; },
+            _ => {},
+        }
+
+        if self.depth > 0 {
+            self.tokens.push((token.clone(), span.clone()));
+            return (true, vec![]);
+        }
+
+        let start = self.start.take().unwrap();
+        let context = self.context();
+        self.reset();
+        (true, vec![(Tagged::Attribute(context), start.join(span))])
+    }
+
+    fn context(&self) -> AttrContext {
+        let name = match self.tokens.first() {
+            Some(&(Token::Ident(ref name), _)) => name.clone(),
+            _ => String::new(),
+        };
+
+        let mut context = AttrContext::new(name, self.inner, self.scope.borrow().path());
+        if context.name != "derive" {
+            return context;
+        }
+
+        // derive(Debug, serde::Serialize)
+        let mut names = vec![];
+        for &(ref token, ref span) in self.tokens.iter().skip(1) {
+            match *token {
+                Token::Ident(ref name) => { names.push((name.clone(), span.clone())); },
+                Token::Colon2 => {},
+                _ => { derive(&mut names, &mut context); },
+            }
+        }
+        derive(&mut names, &mut context);
+
+        context
+    }
+
+    fn reset(&mut self) {
+        self.start = None;
+        self.inner = false;
+        self.depth = 0;
+        self.tokens.clear();
+    }
+}
+
+/// Adds the trait of `names` (modules and the trait name) to the derives.
+fn derive(names: &mut Vec<(String, Span)>, context: &mut AttrContext) {
+    let names = mem::replace(names, vec![]);
+    if let Some(&(_, ref span)) = names.last() {
+        let last = names.len() - 1;
+        let path = names.iter().enumerate().map(|(i, &(ref name, _))| {
+            (if i == last { Token::T_trait } else { Token::T_mod }, name.clone())
+        }).collect();
+        context.derives.push((Path::path(path), span.clone()));
+    }
+}
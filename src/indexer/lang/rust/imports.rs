@@ -0,0 +1,179 @@
+use std::cell::RefCell;
+use std::mem;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use indexer::lexer::{Token, Span};
+use indexer::parser::{Path, Tagged, ImportContext};
+use indexer::lang::rust::scope::ScopeTracker;
+
+/// Parses `use` trees. Every name of the tree is tagged with the path it refers to,
+/// the last names (and the modules imported with `self`) are bound to their local aliases.
+pub struct UseParser {
+    pub buffer: Arc<String>,
+    pub scope: Rc<RefCell<ScopeTracker>>,
+    pub active: bool,
+    pub used_from: Path,
+    pub current: Vec<(String, Option<Span>)>,
+    pub emitted: usize,
+    // Length of the prefix before `{` and the tag of its last name
+    pub groups: Vec<(usize, Option<usize>)>,
+    pub alias: Option<String>,
+    pub expect_alias: bool,
+    pub done: bool,
+    pub tags: Vec<(Tagged, Span)>,
+}
+
+impl UseParser {
+    pub fn new(buffer: Arc<String>, scope: Rc<RefCell<ScopeTracker>>) -> UseParser {
+        UseParser {
+            buffer: buffer,
+            scope: scope,
+            active: false,
+            used_from: Path::path(vec![]),
+            current: vec![],
+            emitted: 0,
+            groups: vec![],
+            alias: None,
+            expect_alias: false,
+            done: false,
+            tags: vec![],
+        }
+    }
+
+    /// Returns tags of the whole declaration when its `;` is reached.
+    pub fn push(&mut self, token: &Token, span: &Span) -> Vec<(Tagged, Span)> {
+        use indexer::lexer::Token::*;
+
+        if !self.active {
+            if let T_use = *token {
+                self.start();
+            }
+            return vec![];
+        }
+
+        let buffer = self.buffer.clone();
+        let text = &buffer[span.lo..span.hi];
+
+        if self.expect_alias {
+            self.expect_alias = false;
+            if let Ident(ref name) = *token {
+                self.alias = Some(name.clone());
+                return vec![];
+            }
+        }
+
+        match *token {
+            Ident(ref name) => {
+                self.current.push((name.clone(), Some(span.clone())));
+            },
+            T_self if self.groups.len() > 0 && self.current.len() == self.base() => {
+                // `use foo::{self, ...}` binds `foo`
+                if let Some(&(_, Some(index))) = self.groups.last() {
+                    if let Tagged::Import(ref mut context) = self.tags[index].0 {
+                        context.alias = context.path.last().cloned();
+                    }
+                }
+                self.done = true;
+            },
+            T_self | T_super | T_crate => {
+                self.current.push((text.to_string(), None));
+            },
+            T_as => {
+                self.expect_alias = true;
+            },
+            LFigureParen => {
+                let len = self.current.len();
+                let before = self.tags.len();
+                self.emit_prefix(len);
+                let last = match self.current.last() {
+                    Some(&(_, Some(_))) if self.tags.len() > before => Some(self.tags.len() - 1),
+                    _ => None,
+                };
+                self.groups.push((len, last));
+            },
+            RFigureParen => {
+                self.finish();
+                if let Some((base, _)) = self.groups.pop() {
+                    self.current.truncate(base);
+                    self.emitted = base;
+                }
+                self.done = true;
+            },
+            Star => {
+                let len = self.current.len();
+                self.emit_prefix(len);
+                let mut path = self.names(len);
+                path.push("*".to_string());
+                let context = ImportContext::new(path, None, self.used_from.clone());
+                self.tags.push((Tagged::Import(context), span.clone()));
+                self.done = true;
+            },
+            Comma => {
+                self.finish();
+                let base = self.base();
+                self.current.truncate(base);
+                self.emitted = base;
+            },
+            Semi => {
+                self.finish();
+                self.active = false;
+                return mem::replace(&mut self.tags, vec![]);
+            },
+            _ => {},
+        }
+
+        vec![]
+    }
+
+    fn start(&mut self) {
+        self.active = true;
+        self.used_from = self.scope.borrow().path();
+        self.current.clear();
+        self.emitted = 0;
+        self.groups.clear();
+        self.alias = None;
+        self.expect_alias = false;
+        self.done = false;
+    }
+
+    fn base(&self) -> usize {
+        match self.groups.last() {
+            Some(&(base, _)) => base,
+            None => 0,
+        }
+    }
+
+    fn names(&self, till: usize) -> Vec<String> {
+        self.current[..till].iter().map(|&(ref name, _)| name.clone()).collect()
+    }
+
+    fn emit(&mut self, at: usize, alias: Option<String>) {
+        if let Some(span) = self.current[at].1 {
+            let context = ImportContext::new(self.names(at + 1), alias, self.used_from.clone());
+            self.tags.push((Tagged::Import(context), span));
+        }
+    }
+
+    fn emit_prefix(&mut self, till: usize) {
+        for i in self.emitted..till {
+            self.emit(i, None);
+        }
+        self.emitted = till;
+    }
+
+    fn finish(&mut self) {
+        let len = self.current.len();
+        if !self.done && len > self.base() {
+            self.emit_prefix(len - 1);
+            let alias = match self.alias.take() {
+                Some(alias) => alias,
+                None => self.current[len - 1].0.clone(),
+            };
+            self.emit(len - 1, Some(alias));
+            self.emitted = len;
+        }
+        self.done = false;
+        self.alias = None;
+    }
+}
@@ -1,20 +1,30 @@
+pub mod scope;
+pub mod imports;
+pub mod attributes;
+
 use std::collections::vec_deque::VecDeque;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::cell::RefCell;
 
-use indexer::lexer::{Token, Span, WhitespaceType, span_in};
+use indexer::lexer::{Token, Span, WhitespaceType, Cursor, offset_in, bom_len, is_ident};
+use unicode_xid::UnicodeXID;
 use indexer::storage::PreparsedFile;
 use indexer::parser::{CommonParser, Tagged, Preprocessing, CPreprocessing, FuzzyParser, FuzzyRule, FuzzyRuleState,
     match_tokens, merge_result, Path, UseContext};
+use self::scope::ScopeTracker;
+use self::imports::UseParser;
+use self::attributes::AttributeParser;
 
 
 pub struct RustParser {
     pub file: String,
-    pub buffer: Rc<String>,
+    pub buffer: Arc<String>,
     pub lexems: Vec<(Token, Span)>,
 }
 
 impl RustParser {
-    pub fn new(file: String, buffer: Rc<String>) -> RustParser {
+    pub fn new(file: String, buffer: Arc<String>) -> RustParser {
         RustParser {
             file: file,
             buffer: buffer,
@@ -29,12 +39,26 @@ lexer! {
     r#"[\n]"# => (Token::Whitespace(WhitespaceType::Newline), text),
     //r#"\r\n"# => (Token::Whitespace(WhitespaceType::Newline), text),
     r#"[ \t]+"# => (Token::Whitespace(WhitespaceType::Spaces), text),
-    // "C-style" comments (/* .. */) - can't contain "*/"
-    r#"/[*](~(.*[*]/.*))[*]/"# => (Token::Comment, text),
-    // "C++-style" comments (// ...)
+    // "C-style" comments (/* .. */) can be nested, they are found by `RustLexer`.
+    // "C++-style" comments (// ...), `////` isn't a doc comment
+    r#"////[^\n]*"# => (Token::Comment, text),
+    r#"///[^\n]*"# => (Token::DocComment, text),
+    r#"//![^\n]*"# => (Token::InnerDocComment, text),
     r#"//[^\n]*"# => (Token::Comment, text),
 
-    r#""[^\n"]*""# => (Token::QuotedString, text),
+    // Strings can contain escaped quotes and newlines. Raw strings are found by `RustLexer`,
+    // regular expressions can't match the numbers of `#` at their ends
+    r#""([^"\\]|\\(.|\n))*""# => (Token::QuotedString, text),
+    r#"b"([^"\\]|\\(.|\n))*""# => (Token::ByteString, text),
+    r#"'([^'\\\n]|\\.[^'\n]*)'"# => (Token::Char, text),
+    r#"b'([^'\\\n]|\\.[^'\n]*)'"# => (Token::Byte, text),
+    // Shorter than chars: `'a'` is a char, `'a` is a lifetime
+    r#"'[a-zA-Z_][a-zA-Z0-9_]*"# => (Token::Lifetime, text),
+
+    r#"[0-9][0-9_]*(\.[0-9][0-9_]*)?([eE][+-]?[0-9_]+)?([iu](8|16|32|64|size)|f32|f64)?"# => (Token::Number, text),
+    r#"0x[0-9a-fA-F_]+([iu](8|16|32|64|size))?"# => (Token::Number, text),
+    r#"0o[0-7_]+([iu](8|16|32|64|size))?"# => (Token::Number, text),
+    r#"0b[01_]+([iu](8|16|32|64|size))?"# => (Token::Number, text),
 
 
     r#"as"# => (Token::T_as, text),
@@ -97,7 +121,55 @@ lexer! {
     r#"{"# => (Token::LFigureParen, text),
     r#"}"# => (Token::RFigureParen, text),
 
+    r#"\["# => (Token::LBracket, text),
+    r#"\]"# => (Token::RBracket, text),
+
     r#"::"# => (Token::Colon2, text),
+    r#":"# => (Token::Colon, text),
+    r#";"# => (Token::Semi, text),
+    r#","# => (Token::Comma, text),
+    r#"#"# => (Token::Pound, text),
+    r#"\."# => (Token::Dot, text),
+    r#"\.\."# => (Token::DotDot, text),
+    r#"\.\.\."# => (Token::DotDotDot, text),
+    r#"\.\.="# => (Token::DotDotEq, text),
+    r#"->"# => (Token::RArrow, text),
+    r#"=>"# => (Token::FatArrow, text),
+    r#"!"# => (Token::Bang, text),
+    r#"\?"# => (Token::Question, text),
+    r#"@"# => (Token::At, text),
+    r#"\$"# => (Token::Dollar, text),
+    r#"\~"# => (Token::Tilde, text),
+
+    r#"="# => (Token::Eq, text),
+    r#"=="# => (Token::EqEq, text),
+    r#"!="# => (Token::Ne, text),
+    r#"<"# => (Token::Lt, text),
+    r#"<="# => (Token::Le, text),
+    r#">"# => (Token::Gt, text),
+    r#">="# => (Token::Ge, text),
+    r#"<<"# => (Token::Shl, text),
+    r#">>"# => (Token::Shr, text),
+    r#"\+"# => (Token::Plus, text),
+    r#"-"# => (Token::Minus, text),
+    r#"\*"# => (Token::Star, text),
+    r#"/"# => (Token::Slash, text),
+    r#"%"# => (Token::Percent, text),
+    r#"\^"# => (Token::Caret, text),
+    r#"\&"# => (Token::And, text),
+    r#"\|"# => (Token::Or, text),
+    r#"\&\&"# => (Token::AndAnd, text),
+    r#"\|\|"# => (Token::OrOr, text),
+    r#"\+="# => (Token::PlusEq, text),
+    r#"-="# => (Token::MinusEq, text),
+    r#"\*="# => (Token::StarEq, text),
+    r#"/="# => (Token::SlashEq, text),
+    r#"%="# => (Token::PercentEq, text),
+    r#"\^="# => (Token::CaretEq, text),
+    r#"\&="# => (Token::AndEq, text),
+    r#"\|="# => (Token::OrEq, text),
+    r#"<<="# => (Token::ShlEq, text),
+    r#">>="# => (Token::ShrEq, text),
 
     r#"."# => (Token::Other, text),
 }
@@ -105,48 +177,144 @@ lexer! {
 pub struct RustLexer<'a> {
     original: &'a str,
     remaining: &'a str,
-    line_counter: usize,
+    cursor: Cursor<'a>,
+    started: bool,
 }
 
 impl<'a> RustLexer<'a> {
     pub fn new(s: &'a str) -> RustLexer<'a> {
-        RustLexer { original: s, remaining: s, line_counter: 0 }
+        RustLexer { original: s, remaining: &s[bom_len(s)..], cursor: Cursor::new(s), started: false }
+    }
+
+    /// Length of the raw string `text` starts with: `r`, `br` and then `"` in
+    /// any number of `#`s, e.g. `r#"a "quoted" string"#`.
+    fn raw_string(text: &str) -> Option<(Token, usize)> {
+        let (token, prefix) = if text.starts_with("br") {
+            (Token::RawByteString, 2)
+        } else if text.starts_with("r") {
+            (Token::RawString, 1)
+        } else {
+            return None;
+        };
+
+        let hashes = text[prefix..].chars().take_while(|&c| c == '#').count();
+        let start = prefix + hashes;
+        if !text[start..].starts_with('"') {
+            return None;
+        }
+
+        let end = format!("\"{}", &text[prefix..start]);
+        text[start + 1..].find(&end).map(|found| (token, start + 1 + found + end.len()))
+    }
+
+    /// Length of the block comment `text` starts with, `/* /* nested */ */` included.
+    /// Unclosed comments last till the end.
+    fn block_comment(text: &str) -> Option<(Token, usize)> {
+        if !text.starts_with("/*") {
+            return None;
+        }
+
+        let bytes = text.as_bytes();
+        let mut depth = 0;
+        let mut i = 0;
+        let mut len = text.len();
+        while i + 1 < bytes.len() {
+            if bytes[i] == b'/' && bytes[i + 1] == b'*' {
+                depth += 1;
+                i += 2;
+            } else if bytes[i] == b'*' && bytes[i + 1] == b'/' {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    len = i;
+                    break;
+                }
+            } else {
+                i += 1;
+            }
+        }
+
+        let comment = &text[..len];
+        let token = if comment.starts_with("/*!") {
+            Token::InnerDocComment
+        } else if comment.starts_with("/**") && !comment.starts_with("/***") && comment != "/**/" {
+            Token::DocComment
+        } else {
+            Token::Comment
+        };
+        Some((token, len))
+    }
+
+    /// The lexer rules only know ASCII identifiers, so a lexem is extended with
+    /// the following non-ASCII XID_Continue characters and a non-ASCII XID_Start
+    /// character starts an identifier, e.g. `fn` + `ü` and `д` + `ом`.
+    /// Lifetimes are extended the same way: `'` + `ä`.
+    fn extend_ident(&mut self, tok: Token, text: &'a str) -> (Token, &'a str) {
+        let lifetime = match tok {
+            Token::Lifetime => true,
+            Token::Other if text == "'" => self.remaining.chars().next().map(UnicodeXID::is_xid_start).unwrap_or(false),
+            _ => false,
+        };
+        if !lifetime && !is_ident(text) {
+            return (tok, text);
+        }
+
+        let len = self.remaining.char_indices()
+            .find(|&(_, c)| !UnicodeXID::is_xid_continue(c))
+            .map(|(i, _)| i)
+            .unwrap_or(self.remaining.len());
+
+        let other = match tok { Token::Other => true, _ => false };
+        if len == 0 && !other {
+            return (tok, text);
+        }
+
+        let lo = offset_in(text, self.original);
+        let ident = &self.original[lo..lo + text.len() + len];
+        self.remaining = &self.remaining[len..];
+        if lifetime {
+            (Token::Lifetime, ident)
+        } else {
+            (Token::Ident(ident.to_owned()), ident)
+        }
     }
 }
 
 impl<'a> Iterator for RustLexer<'a> {
     type Item = (Token, Span);
     fn next(&mut self) -> Option<(Token, Span)> {
-        if self.line_counter == 0 {
-            self.line_counter = 1;
+        if !self.started {
+            self.started = true;
+            // The first line starts without a newline, after the byte order mark
+            let start = bom_len(self.original);
             let item = Some((
                 Token::Whitespace(WhitespaceType::Newline),
-                Span {
-                    lo: 0,
-                    hi: 0,
-                    line: self.line_counter,
-                }
+                self.cursor.advance(start, start),
             ));
             return item
         }
 
+        if let Some((tok, len)) = RustLexer::raw_string(self.remaining).or_else(|| RustLexer::block_comment(self.remaining)) {
+            let lo = offset_in(self.remaining, self.original);
+            self.remaining = &self.remaining[len..];
+            return Some((tok, self.cursor.advance(lo, lo + len)));
+        }
+
         loop {
-            if let Some((tok, span)) = next_token(&mut self.remaining) {
-                match &tok {
-                    &Token::Whitespace(WhitespaceType::Newline) => {
-                        self.line_counter += 1;
-                    },
-                    _ => {},
-                }
-                return Some((tok, span_in(span, self.original, self.line_counter)));
+            if let Some((tok, text)) = next_token(&mut self.remaining) {
+                let (tok, text) = self.extend_ident(tok, text);
+                let lo = offset_in(text, self.original);
+                let hi = lo + text.len();
+                let span = match &tok {
+                    &Token::Whitespace(WhitespaceType::Newline) => self.cursor.newline(lo, hi),
+                    _ => self.cursor.advance(lo, hi),
+                };
+                return Some((tok, span));
             } else {
+                let end = self.original.len();
                 return Some((
                     Token::Eof,
-                    Span {
-                        lo: self.original.len(),
-                        hi: self.original.len(),
-                        line: self.line_counter,
-                    }
+                    self.cursor.advance(end, end),
                 ))
             };
         }
@@ -154,8 +322,29 @@ impl<'a> Iterator for RustLexer<'a> {
 }
 
 
-pub struct KwMatch;
-pub struct FnMatch;
+pub struct KwMatch {
+    pub scope: Rc<RefCell<ScopeTracker>>,
+}
+
+impl KwMatch {
+    pub fn new(scope: Rc<RefCell<ScopeTracker>>) -> KwMatch {
+        KwMatch {
+            scope: scope,
+        }
+    }
+}
+
+pub struct FnMatch {
+    pub scope: Rc<RefCell<ScopeTracker>>,
+}
+
+impl FnMatch {
+    pub fn new(scope: Rc<RefCell<ScopeTracker>>) -> FnMatch {
+        FnMatch {
+            scope: scope,
+        }
+    }
+}
 
 impl CommonParser for RustParser {
     fn parse(&mut self) -> PreparsedFile {
@@ -176,14 +365,24 @@ impl CommonParser for RustParser {
 
         let mut preproc = CPreprocessing{};
 
-        let kw_rule = Box::new(KwMatch{});
-        let fn_rule = Box::new(FnMatch{});
+        let scope = Rc::new(RefCell::new(ScopeTracker::new()));
+
+        let kw_rule = Box::new(KwMatch::new(scope.clone()));
+        let fn_rule = Box::new(FnMatch::new(scope.clone()));
+        let mut use_parser = UseParser::new(self.buffer.clone(), scope.clone());
+        let mut attribute_parser = AttributeParser::new(scope.clone());
 
         let mut parser = FuzzyParser::new(vec![fn_rule]);
         let mut syntax_parser = FuzzyParser::new(vec![kw_rule]);
 
         let mut syntax_parser_out = vec![];
-        let mut parser_out = vec![];
+        let mut parser_out: Vec<(Tagged, Span)> = vec![];
+        // Outer doc comments waiting for the definition they document
+        let mut doc = vec![];
+        let mut docs = vec![];
+        // Inner doc comments of the file and of the spans of the modules they are in
+        let mut module_doc = vec![];
+        let mut inner_docs: Vec<(Span, Vec<String>)> = vec![];
 
         for &(ref tok, ref span) in &self.lexems {
             let lsyn = syntax_parser.push((tok, span));
@@ -192,22 +391,133 @@ impl CommonParser for RustParser {
                 syntax_parser_out.extend(lsyn);
             }
 
+            if let &Token::DocComment = tok {
+                doc.push(doc_text(&self.buffer[span.lo..span.hi]));
+            }
+
+            if let &Token::InnerDocComment = tok {
+                let text = doc_text(&self.buffer[span.lo..span.hi]);
+                let path = scope.borrow().path();
+                let module = parser_out.iter().rev().find(|&&(ref tagged, _)| {
+                    match tagged { &Tagged::Definition(ref context) => context.reference == path, _ => false }
+                }).map(|&(_, ref span)| span.clone());
+
+                match module {
+                    _ if path.path.len() == 0 => { module_doc.push(text); },
+                    Some(module) => {
+                        let same = inner_docs.last().map(|&(ref last, _)| last.lo == module.lo).unwrap_or(false);
+                        if same {
+                            inner_docs.last_mut().unwrap().1.push(text);
+                        } else {
+                            inner_docs.push((module, vec![text]));
+                        }
+                    },
+                    None => {},
+                }
+            }
+
             if let Some((wtok, wspan)) = preproc.filter((tok, span)) {
-                let pres = parser.push((wtok, wspan));
-                if pres.len() != 0 {
-                    //println!("PR: {:?}", res);
-                    parser_out.extend(pres);
+                let start = parser_out.len();
+
+                // Attributes aren't code, `derive(Debug)` isn't a call
+                let (in_attribute, attributes) = attribute_parser.push(wtok, wspan);
+                parser_out.extend(attributes);
+
+                if !in_attribute {
+                    let pres = parser.push((wtok, wspan));
+                    if pres.len() != 0 {
+                        //println!("PR: {:?}", res);
+                        parser_out.extend(pres);
+                    }
+                }
+
+                let imports = use_parser.push(wtok, wspan);
+                parser_out.extend(imports);
+
+                let scoped = scope.borrow_mut().push(wtok, wspan);
+                parser_out.extend(scoped);
+
+                if doc.len() > 0 {
+                    let definition = parser_out[start..].iter().find(|&&(ref tagged, _)| {
+                        match tagged { &Tagged::Definition(_) => true, _ => false }
+                    }).map(|&(_, ref span)| span.clone());
+
+                    match definition {
+                        Some(definition) => {
+                            docs.push((definition, doc.join("\n")));
+                            doc.clear();
+                        },
+                        // The documented item is not a definition, e.g. a field
+                        None if !in_attribute => {
+                            match *wtok {
+                                Token::Semi | Token::Comma | Token::LFigureParen | Token::RFigureParen => { doc.clear(); },
+                                _ => {},
+                            }
+                        },
+                        None => {},
+                    }
                 }
             }
         }
 
+        // Implementations and imports are tagged only when their blocks or declarations end.
+        // `Foo` of `impl Foo {` is an implementation rather than a struct literal
+        parser_out.sort_by(|a, b| {
+            let rank = |tagged: &Tagged| match tagged { &Tagged::Implementation(_) => 0, _ => 1 };
+            (a.1.lo, rank(&a.0)).cmp(&(b.1.lo, rank(&b.0)))
+        });
+        let mut last_hi = 0;
+        parser_out.retain(|&(_, ref span)| {
+            if span.lo < last_hi {
+                return false;
+            }
+            last_hi = span.hi;
+            true
+        });
+
         //println!("SYN: {:?}", syntax_parser_out);
         //println!("PRS: {:?}", parser_out);
 
-        PreparsedFile::new(self.file.clone(), self.buffer.clone(), syntax_parser_out, parser_out)
+        let modules = scope.borrow().modules.clone();
+
+        for (module, lines) in inner_docs {
+            // Outer and inner docs of the same module are joined as paragraphs
+            let inner = lines.join("\n");
+            let outer = docs.iter().position(|&(ref span, _)| span.lo == module.lo);
+            match outer {
+                Some(i) => { docs[i].1 = format!("{}\n\n{}", docs[i].1, inner); },
+                None => { docs.push((module, inner)); },
+            }
+        }
+
+        let mut preparsed = PreparsedFile::new(self.file.clone(), self.buffer.clone(), syntax_parser_out, parser_out, modules);
+        preparsed.docs = docs;
+        if module_doc.len() > 0 {
+            preparsed.module_doc = Some(module_doc.join("\n"));
+        }
+        preparsed
     }
 }
 
+/// Text of the doc comment without `///`, `//!`, `/** */` or `/*! */` and the leading stars of its lines.
+pub fn doc_text(comment: &str) -> String {
+    if comment.starts_with("///") || comment.starts_with("//!") {
+        let line = &comment[3..];
+        return if line.starts_with(' ') { line[1..].to_string() } else { line.to_string() };
+    }
+
+    let body = comment[3..].trim_right_matches("*/");
+    let lines: Vec<&str> = body.lines().map(|line| {
+        let line = line.trim();
+        let line = if line.starts_with('*') { &line[1..] } else { line };
+        if line.starts_with(' ') { &line[1..] } else { line }
+    }).collect();
+
+    let first = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+    let last = lines.iter().rposition(|line| !line.is_empty()).map(|i| i + 1).unwrap_or(first);
+    lines[first..last].join("\n")
+}
+
 
 impl<'a> FuzzyRule<'a> for KwMatch {
     fn match_tokens(&mut self, tokens: &VecDeque<(&'a Token, &'a Span)>) -> FuzzyRuleState {
@@ -230,10 +540,38 @@ impl<'a> FuzzyRule<'a> for KwMatch {
                 1,
                 vec![(Tagged::Comment, tokens[0].1.clone())],
             ),
-            &QuotedString => FuzzyRuleState::Ready(
+            &DocComment | &InnerDocComment => FuzzyRuleState::Ready(
+                1,
+                vec![(Tagged::DocComment, tokens[0].1.clone())],
+            ),
+            &QuotedString | &RawString | &ByteString | &RawByteString => FuzzyRuleState::Ready(
                 1,
                 vec![(Tagged::QuotedString, tokens[0].1.clone())],
             ),
+            &Char | &Byte => FuzzyRuleState::Ready(
+                1,
+                vec![(Tagged::Char, tokens[0].1.clone())],
+            ),
+            &Number => FuzzyRuleState::Ready(
+                1,
+                vec![(Tagged::Number, tokens[0].1.clone())],
+            ),
+            &Lifetime => FuzzyRuleState::Ready(
+                1,
+                vec![(Tagged::Lifetime, tokens[0].1.clone())],
+            ),
+            // Brackets of generics, `!` of macro calls and `&` or `*` of types aren't operators
+            &Lt | &Shl if self.scope.borrow().opens_generics() => FuzzyRuleState::NotMatches,
+            &Gt | &Shr if self.scope.borrow().in_generics() => FuzzyRuleState::NotMatches,
+            &Bang if self.scope.borrow().after_name() => FuzzyRuleState::NotMatches,
+            &And | &AndAnd | &Star if self.scope.borrow().in_type_position() => FuzzyRuleState::NotMatches,
+            &DotDot | &DotDotDot | &DotDotEq | &RArrow | &FatArrow | &Bang | &Question | &At | &Tilde |
+            &Eq | &EqEq | &Ne | &Lt | &Le | &Gt | &Ge | &Shl | &Shr | &Plus | &Minus | &Star | &Slash |
+            &Percent | &Caret | &And | &Or | &AndAnd | &OrOr | &PlusEq | &MinusEq | &StarEq | &SlashEq |
+            &PercentEq | &CaretEq | &AndEq | &OrEq | &ShlEq | &ShrEq => FuzzyRuleState::Ready(
+                1,
+                vec![(Tagged::Operator, tokens[0].1.clone())],
+            ),
             &Whitespace(ref wh) => {
                 match wh {
                     &WhitespaceType::Newline => {
@@ -260,10 +598,113 @@ impl<'a> FuzzyRule<'a> for FnMatch {
         //println!("Q: {:?}", tokens);
         let mut res = FuzzyRuleState::NotMatches;
 
-        let cur_context = Path::named(T_mod, ".".to_string());
+        let cur_context = self.scope.borrow().path();
+
+        let definitions = vec![
+            (vec![T_fn, Ident(String::new())], T_fn),
+            (vec![T_struct, Ident(String::new())], T_struct),
+            (vec![T_enum, Ident(String::new())], T_enum),
+            (vec![T_trait, Ident(String::new())], T_trait),
+            (vec![T_type, Ident(String::new())], T_type),
+            (vec![T_const, Ident(String::new())], T_const),
+            (vec![T_static, Ident(String::new())], T_static),
+            (vec![T_static, T_mut, Ident(String::new())], T_static),
+            (vec![T_mod, Ident(String::new())], T_mod),
+        ];
+
+        for (rr, kind) in definitions {
+            let mut cur_match = match_tokens(&rr, tokens);
+
+            match cur_match {
+                FuzzyRuleState::Cont(len) if tokens.len() >= len => {
+                    let name_at = rr.len() - 1;
+                    let mut name = String::new();
+                    match tokens[name_at].0 {
+                        &Token::Ident(ref n) => { name = n.clone(); },
+                        _ => {},
+                    }
+
+                    cur_match = FuzzyRuleState::Ready(
+                        rr.len(),
+                        vec![(Tagged::Definition(UseContext::new(cur_context.join(kind, name), cur_context.clone())), tokens[name_at].1.clone())],
+                    );
+                },
+                _ => {},
+            }
+            res = merge_result(cur_match, res);
+        }
+
+        // Raw pointers `*const T` and `*mut T` aren't definitions, both tokens are eaten
+        for rr in vec![vec![Star, T_const], vec![Star, T_mut]] {
+            let mut cur_match = match_tokens(&rr, tokens);
+
+            match cur_match {
+                FuzzyRuleState::Cont(len) if tokens.len() >= len => {
+                    cur_match = FuzzyRuleState::Ready(rr.len(), vec![]);
+                },
+                _ => {},
+            }
+            res = merge_result(cur_match, res);
+        }
 
         {
-            let rr = vec![T_fn, Ident(String::new()), LParen];
+            // macro_rules! NAME
+            let rr = vec![Ident(String::new()), Bang, Ident(String::new())];
+            let mut cur_match = match tokens[0].0 {
+                &Token::Ident(ref n) if n == "macro_rules" => match_tokens(&rr, tokens),
+                _ => FuzzyRuleState::NotMatches,
+            };
+
+            match cur_match {
+                FuzzyRuleState::Cont(len) if tokens.len() >= len => {
+                    let mut name = String::new();
+                    match tokens[2].0 {
+                        &Token::Ident(ref n) => { name = n.clone(); },
+                        _ => {},
+                    }
+
+                    cur_match = FuzzyRuleState::Ready(
+                        rr.len(),
+                        vec![(Tagged::Definition(UseContext::new(cur_context.join(T_macro, name), cur_context.clone())), tokens[2].1.clone())],
+                    );
+                },
+                _ => {},
+            }
+            res = merge_result(cur_match, res);
+        }
+
+        // let x = ..., let mut x: T; but not patterns like `let Some(x)`, the token after the name isn't eaten
+        let mut locals = vec![];
+        for follower in vec![Eq, Colon, Semi] {
+            locals.push(vec![T_let, Ident(String::new()), follower.clone()]);
+            locals.push(vec![T_let, T_mut, Ident(String::new()), follower]);
+        }
+        for rr in locals {
+            let mut cur_match = match_tokens(&rr, tokens);
+
+            match cur_match {
+                FuzzyRuleState::Cont(len) if tokens.len() >= len => {
+                    let name_at = rr.len() - 2;
+                    let mut name = String::new();
+                    match tokens[name_at].0 {
+                        &Token::Ident(ref n) => { name = n.clone(); },
+                        _ => {},
+                    }
+
+                    cur_match = FuzzyRuleState::Ready(
+                        rr.len() - 1,
+                        vec![(Tagged::Local(name), tokens[name_at].1.clone())],
+                    );
+                },
+                _ => {},
+            }
+            res = merge_result(cur_match, res);
+        }
+
+        // Types after `->` and capitalized types after `:`, but not constants like `MAX`,
+        // the modules of paths like `io::Result` or bounds like `T: Clone` in generics
+        let in_generics = self.scope.borrow().in_generics();
+        for (rr, any) in vec![(vec![RArrow, Ident(String::new())], true), (vec![Colon, Ident(String::new())], false)] {
             let mut cur_match = match_tokens(&rr, tokens);
 
             match cur_match {
@@ -274,9 +715,65 @@ impl<'a> FuzzyRule<'a> for FnMatch {
                         _ => {},
                     }
 
+                    let is_type = (any || scope::is_type_name(&name)) && !in_generics;
+                    cur_match = match tokens.get(2) {
+                        _ if !is_type => FuzzyRuleState::NotMatches,
+                        None => FuzzyRuleState::Cont(3),
+                        Some(&(&Colon2, _)) => FuzzyRuleState::NotMatches,
+                        Some(_) => FuzzyRuleState::Ready(
+                            rr.len(),
+                            vec![(Tagged::TypeRef(UseContext::new(Path::named(T_struct, name), cur_context.clone())), tokens[1].1.clone())],
+                        ),
+                    };
+                },
+                _ => {},
+            }
+            res = merge_result(cur_match, res);
+        }
+
+        {
+            // .name not followed by `(` of a method call
+            let rr = vec![Dot, Ident(String::new())];
+            let mut cur_match = match_tokens(&rr, tokens);
+
+            match cur_match {
+                FuzzyRuleState::Cont(len) if tokens.len() >= len => {
+                    let mut name = String::new();
+                    match tokens[1].0 {
+                        &Token::Ident(ref n) => { name = n.clone(); },
+                        _ => {},
+                    }
+
+                    cur_match = match tokens.get(2) {
+                        None => FuzzyRuleState::Cont(3),
+                        Some(&(&LParen, _)) => FuzzyRuleState::NotMatches,
+                        Some(_) => FuzzyRuleState::Ready(
+                            rr.len(),
+                            vec![(Tagged::Field(name), tokens[1].1.clone())],
+                        ),
+                    };
+                },
+                _ => {},
+            }
+            res = merge_result(cur_match, res);
+        }
+
+        // name!(...), name![...] and name!{...}, calls in the arguments are parsed after them
+        for open in vec![LParen, LBracket, LFigureParen] {
+            let rr = vec![Ident(String::new()), Bang, open];
+            let mut cur_match = match_tokens(&rr, tokens);
+
+            match cur_match {
+                FuzzyRuleState::Cont(len) if tokens.len() >= len => {
+                    let mut name = String::new();
+                    match tokens[0].0 {
+                        &Token::Ident(ref n) => { name = n.clone(); },
+                        _ => {},
+                    }
+
                     cur_match = FuzzyRuleState::Ready(
                         rr.len(),
-                        vec![(Tagged::Definition(UseContext::new(Path::named(T_fn, name), cur_context.clone())), tokens[1].1.clone())],
+                        vec![(Tagged::MacroCall(UseContext::new(Path::named(T_macro, name), cur_context.clone())), tokens[0].1.clone())],
                     );
                 },
                 _ => {},
@@ -285,7 +782,7 @@ impl<'a> FuzzyRule<'a> for FnMatch {
         }
 
         {
-            let rr = vec![T_struct, Ident(String::new()), LFigureParen];
+            let rr = vec![Dot, Ident(String::new()), LParen];
             let mut cur_match = match_tokens(&rr, tokens);
 
             match cur_match {
@@ -298,7 +795,7 @@ impl<'a> FuzzyRule<'a> for FnMatch {
 
                     cur_match = FuzzyRuleState::Ready(
                         rr.len(),
-                        vec![(Tagged::Definition(UseContext::new(Path::named(T_struct, name), cur_context.clone())), tokens[1].1.clone())],
+                        vec![(Tagged::MethodCall(UseContext::new(Path::named(T_fn, name), cur_context.clone())), tokens[1].1.clone())],
                     );
                 },
                 _ => {},
@@ -0,0 +1,283 @@
+use std::mem;
+
+use indexer::lexer::{Token, Span};
+use indexer::parser::{Path, Tagged, UseContext, ImplContext};
+
+#[derive(Debug)]
+pub struct ImplHeader {
+    pub angles: usize,
+    pub collecting: bool,
+    pub trait_name: Option<(String, Span)>,
+    pub self_type: Option<(String, Span)>,
+}
+
+impl ImplHeader {
+    pub fn new() -> ImplHeader {
+        ImplHeader {
+            angles: 0,
+            collecting: true,
+            trait_name: None,
+            self_type: None,
+        }
+    }
+
+    // Generics are skipped, the last identifier on the top level is the type name:
+    // `impl<'a> parser::Path<'a> for Foo` gives `Foo`.
+    pub fn push(&mut self, token: &Token, span: &Span) {
+        use indexer::lexer::Token::*;
+
+        match *token {
+            Ident(ref name) if self.angles == 0 && self.collecting => {
+                self.self_type = Some((name.clone(), span.clone()));
+            },
+            T_for if self.angles == 0 => {
+                self.trait_name = self.self_type.take();
+            },
+            T_where => {
+                self.collecting = false;
+            },
+            Lt => { self.angles += 1; },
+            Gt => { self.angles = self.angles.saturating_sub(1); },
+            Shl => { self.angles += 2; },
+            Shr => { self.angles = self.angles.saturating_sub(2); },
+            _ => {},
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ScopeState {
+    Wait,
+    KeywordThenName(Token),
+    ImplHeader(ImplHeader),
+}
+
+#[derive(Debug)]
+pub struct Scope {
+    pub segment: (Token, String),
+    pub depth: usize,
+    pub implementation: Option<(ImplContext, Span)>,
+}
+
+/// Follows braces of the file and keeps the stack of named blocks
+/// (`mod`, `impl`, `trait`, `enum` and `fn`) the current token is in.
+pub struct ScopeTracker {
+    pub stack: Vec<Scope>,
+    pub state: ScopeState,
+    pub pending: Option<(Token, String)>,
+    pub pending_impl: Option<(ImplContext, Span)>,
+    pub modules: Vec<Path>,
+    pub depth: usize,
+    pub nesting: usize,
+    pub expect_variant: bool,
+    /// Depth of angle brackets of generics: parameters of item headers (`fn foo<T: Clone>`)
+    /// and arguments of types and paths (`Vec<u8>`, `collect::<Vec<_>>()`)
+    pub generics: usize,
+    pub expect_generics: bool,
+    /// The last token `push` was called with
+    pub previous: Token,
+}
+
+impl ScopeTracker {
+    pub fn new() -> ScopeTracker {
+        ScopeTracker {
+            stack: vec![],
+            state: ScopeState::Wait,
+            pending: None,
+            pending_impl: None,
+            modules: vec![],
+            depth: 0,
+            nesting: 0,
+            expect_variant: false,
+            generics: 0,
+            expect_generics: false,
+            previous: Token::Eof,
+        }
+    }
+
+    pub fn path(&self) -> Path {
+        Path::path(self.stack.iter().map(|scope| scope.segment.clone()).collect())
+    }
+
+    /// Whether the current token is in generics.
+    pub fn in_generics(&self) -> bool {
+        self.generics > 0
+    }
+
+    /// Whether `<` at the current token opens generics rather than compares.
+    pub fn opens_generics(&self) -> bool {
+        self.generics > 0 || self.expect_generics || match self.previous {
+            Token::Colon2 => true,
+            Token::Ident(ref name) => is_type_name(name),
+            _ => false,
+        }
+    }
+
+    /// Whether the current token starts a type, e.g. `&` of `v: &Vec<u8>` or `*` of `-> *const u8`.
+    pub fn in_type_position(&self) -> bool {
+        use indexer::lexer::Token::*;
+
+        match self.previous {
+            Colon | RArrow | T_as => true,
+            Lt | Shl | Comma => self.generics > 0,
+            _ => false,
+        }
+    }
+
+    /// Whether the current token follows a name, so `!` is of a macro call rather than a negation.
+    pub fn after_name(&self) -> bool {
+        match self.previous {
+            Token::Ident(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `Tagged::Implementation` when an implementation block is closed
+    /// and definitions of enum variants.
+    pub fn push(&mut self, token: &Token, span: &Span) -> Vec<(Tagged, Span)> {
+        use indexer::lexer::Token::*;
+
+        let mut res = vec![];
+        self.push_generics(token);
+        self.previous = token.clone();
+
+        let state = mem::replace(&mut self.state, ScopeState::Wait);
+        match state {
+            ScopeState::KeywordThenName(keyword) => {
+                if let &Ident(ref name) = token {
+                    if let T_fn = keyword {
+                        self.add_item(name, span);
+                    }
+                    self.pending = Some((keyword, name.clone()));
+                    self.nesting = 0;
+                    return res;
+                }
+            },
+            ScopeState::ImplHeader(mut header) => {
+                match *token {
+                    LFigureParen => {
+                        if let Some((name, span)) = header.self_type {
+                            let self_type = self.path().join(T_struct, name.clone());
+                            // Trait implementations are tagged at the trait names, inherent ones at the types
+                            self.pending_impl = Some(match header.trait_name {
+                                Some((trait_name, trait_span)) => (ImplContext::new(Path::named(T_trait, trait_name), self_type), trait_span),
+                                None => (ImplContext::new(Path::path(vec![]), self_type), span),
+                            });
+                            self.pending = Some((T_struct, name));
+                        }
+                    },
+                    _ => {
+                        header.push(token, span);
+                        self.state = ScopeState::ImplHeader(header);
+                        return res;
+                    },
+                }
+            },
+            ScopeState::Wait => {},
+        }
+
+        match *token {
+            T_mod | T_trait | T_enum | T_fn if self.pending.is_none() => {
+                self.state = ScopeState::KeywordThenName(token.clone());
+            },
+            T_impl if self.pending.is_none() => {
+                self.state = ScopeState::ImplHeader(ImplHeader::new());
+            },
+            LParen => {
+                self.nesting += 1;
+            },
+            RParen => {
+                self.nesting = self.nesting.saturating_sub(1);
+            },
+            LFigureParen => {
+                self.depth += 1;
+                if let Some(segment) = self.pending.take() {
+                    self.expect_variant = match segment.0 {
+                        T_enum => true,
+                        _ => false,
+                    };
+                    self.stack.push(Scope {
+                        segment: segment,
+                        depth: self.depth,
+                        implementation: self.pending_impl.take(),
+                    });
+                }
+            },
+            RFigureParen => {
+                let close = match self.stack.last() {
+                    Some(scope) => scope.depth == self.depth,
+                    None => false,
+                };
+                if close {
+                    if let Some(Scope { implementation: Some((context, span)), .. }) = self.stack.pop() {
+                        res.push((Tagged::Implementation(context), span));
+                    }
+                }
+                self.depth = self.depth.saturating_sub(1);
+            },
+            Ident(ref name) if self.expect_variant && self.in_enum_body() => {
+                let cur_context = self.path();
+                let definition = UseContext::new(cur_context.join(Ident(String::new()), name.clone()), cur_context);
+                res.push((Tagged::Definition(definition), span.clone()));
+                self.expect_variant = false;
+            },
+            LBracket => { self.nesting += 1; },
+            RBracket => { self.nesting = self.nesting.saturating_sub(1); },
+            Comma if self.in_enum_body() => { self.expect_variant = true; },
+            // `mod foo;` or a trait method without a body
+            Semi if self.nesting == 0 => {
+                if let Some((T_mod, name)) = self.pending.take() {
+                    let declared = self.path().join(T_mod, name);
+                    self.modules.push(declared);
+                }
+            },
+            _ => {},
+        }
+
+        res
+    }
+
+    // Generics follow `impl`, the name of the item (`struct Foo<'a, T: Clone>`),
+    // type names (`Vec<u8>`) and `::` (`collect::<Vec<_>>()`)
+    fn push_generics(&mut self, token: &Token) {
+        use indexer::lexer::Token::*;
+
+        match *token {
+            Lt if self.opens_generics() => { self.generics += 1; },
+            Shl if self.opens_generics() => { self.generics += 2; },
+            Gt => { self.generics = self.generics.saturating_sub(1); },
+            Shr => { self.generics = self.generics.saturating_sub(2); },
+            // Comparisons taken for generics (`x < N`) end with their statements
+            LFigureParen | RFigureParen => { self.generics = 0; },
+            _ => {},
+        }
+
+        self.expect_generics = match *token {
+            T_fn | T_struct | T_enum | T_trait | T_type | T_impl => true,
+            Ident(_) => self.expect_generics,
+            _ => false,
+        };
+    }
+
+    fn in_enum_body(&self) -> bool {
+        match self.stack.last() {
+            Some(&Scope { segment: (Token::T_enum, _), depth, .. }) => depth == self.depth && self.nesting == 0,
+            _ => false,
+        }
+    }
+
+    fn add_item(&mut self, name: &str, span: &Span) {
+        let depth = self.depth;
+        if let Some(&mut Scope { implementation: Some((ref mut context, _)), depth: scope_depth, .. }) = self.stack.last_mut() {
+            if scope_depth == depth {
+                context.items.push((name.to_string(), span.clone()));
+            }
+        }
+    }
+}
+
+/// Capitalized names with lowercase letters or of one letter are types: `Point` or `T`, but not `MAX`.
+pub fn is_type_name(name: &str) -> bool {
+    name.chars().next().map(|c| c.is_uppercase()).unwrap_or(false) &&
+        (name.chars().count() == 1 || name.chars().any(|c| c.is_lowercase()))
+}
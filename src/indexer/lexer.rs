@@ -1,3 +1,5 @@
+use unicode_xid::UnicodeXID;
+
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum WhitespaceType {
@@ -13,6 +15,20 @@ pub enum Token {
     NoToken,
     Ident(String),
     QuotedString,
+    /// `r"..."` and `r#"..."#`
+    RawString,
+    ByteString,
+    RawByteString,
+    /// Character literal, `'x'` or `'\n'`
+    Char,
+    /// Byte literal, `b'x'`
+    Byte,
+    /// Integer or float literal with an optional suffix, e.g. `0xFF_u8` or `1e-3`
+    Number,
+    /// Lifetime or loop label, `'a` or `'outer`
+    Lifetime,
+    /// `#` starting attributes
+    Pound,
 
     T_as,
     T_break,
@@ -72,37 +88,204 @@ pub enum Token {
     LFigureParen,
     RFigureParen,
 
+    LBracket,
+    RBracket,
+
     Colon2,
+    Colon,
+    Semi,
+    Comma,
+    Dot,
+    /// `..`
+    DotDot,
+    /// `...`
+    DotDotDot,
+    /// `..=`
+    DotDotEq,
+    /// `->`
+    RArrow,
+    /// `=>`
+    FatArrow,
+    /// `!` of macro calls and negation
+    Bang,
+    Question,
+    At,
+    Dollar,
+    Tilde,
+
+    Eq,
+    EqEq,
+    Ne,
+    /// `<`, also opens generic arguments
+    Lt,
+    Le,
+    /// `>`, also closes generic arguments
+    Gt,
+    Ge,
+    /// `<<`, two `<` in generic arguments
+    Shl,
+    /// `>>`, two `>` in generic arguments
+    Shr,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    And,
+    Or,
+    AndAnd,
+    OrOr,
+    PlusEq,
+    MinusEq,
+    StarEq,
+    SlashEq,
+    PercentEq,
+    CaretEq,
+    AndEq,
+    OrEq,
+    ShlEq,
+    ShrEq,
 
     Whitespace(WhitespaceType),
     Comment,
+    /// `///` or `/** */` documenting the next item
+    DocComment,
+    /// `//!` or `/*! */` documenting the enclosing item
+    InnerDocComment,
 
     Eof,
+    /// Any other character
     Other,
 }
 
+/// Byte range of the lexem with its start and end positions. Lines and columns start from 1,
+/// the end column is the one after the last character. Columns are counted both in bytes
+/// and in UTF-16 code units editors and LSP clients use.
 #[derive(Debug, Clone, Copy)]
 pub struct Span {
     pub lo: usize,
     pub hi: usize,
     pub line: usize,
+    pub col: usize,
+    pub col_utf16: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+    pub end_col_utf16: usize,
 }
 
-pub fn span_in(s: &str, t: &str, line: usize) -> Span {
-    let lo = s.as_ptr() as usize - t.as_ptr() as usize;
-    Span {
-        lo: lo,
-        hi: lo + s.len(),
-        line: line,
+impl Span {
+    /// Span from the start of this one to the end of `last`.
+    pub fn join(&self, last: &Span) -> Span {
+        Span {
+            lo: self.lo,
+            hi: last.hi,
+            line: self.line,
+            col: self.col,
+            col_utf16: self.col_utf16,
+            end_line: last.end_line,
+            end_col: last.end_col,
+            end_col_utf16: last.end_col_utf16,
+        }
     }
-}
 
-impl Span {
     pub fn end() -> Span {
         Span {
             lo: usize::max_value(),
             hi: usize::max_value(),
             line: usize::max_value(),
+            col: usize::max_value(),
+            col_utf16: usize::max_value(),
+            end_line: usize::max_value(),
+            end_col: usize::max_value(),
+            end_col_utf16: usize::max_value(),
+        }
+    }
+}
+
+/// Byte order mark some editors put at the start of UTF-8 files.
+pub const BOM: &'static str = "\u{feff}";
+
+/// Length of the byte order mark `text` starts with, if any.
+pub fn bom_len(text: &str) -> usize {
+    if text.starts_with(BOM) { BOM.len() } else { 0 }
+}
+
+/// Checks if `text` is an identifier: `_` or XID_Start followed by XID_Continue characters.
+pub fn is_ident(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c == '_' || UnicodeXID::is_xid_start(c) => chars.all(UnicodeXID::is_xid_continue),
+        _ => false,
+    }
+}
+
+/// Offset of `s` in `t`, `s` must be a slice of `t`.
+pub fn offset_in(s: &str, t: &str) -> usize {
+    s.as_ptr() as usize - t.as_ptr() as usize
+}
+
+/// Tracks the line and the column of lexems going one by one through the text.
+pub struct Cursor<'a> {
+    text: &'a str,
+    line: usize,
+    /// Offset of the first byte of the line
+    line_start: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// The byte order mark isn't counted in columns of the first line.
+    pub fn new(text: &'a str) -> Cursor<'a> {
+        Cursor {
+            text: text,
+            line: 1,
+            line_start: bom_len(text),
+        }
+    }
+
+    /// Span of `text[lo..hi]` starting at the cursor line, moves the cursor to its end.
+    pub fn advance(&mut self, lo: usize, hi: usize) -> Span {
+        let line = self.line;
+        let col = lo - self.line_start + 1;
+        let col_utf16 = self.utf16_col(lo);
+
+        for (i, c) in self.text[lo..hi].char_indices() {
+            if c == '\n' {
+                self.line += 1;
+                self.line_start = lo + i + 1;
+            }
+        }
+
+        Span {
+            lo: lo,
+            hi: hi,
+            line: line,
+            col: col,
+            col_utf16: col_utf16,
+            end_line: self.line,
+            end_col: hi - self.line_start + 1,
+            end_col_utf16: self.utf16_col(hi),
         }
     }
+
+    /// Span of the newline `text[lo..hi]`. Newlines are placed at the start of the lines they begin.
+    pub fn newline(&mut self, lo: usize, hi: usize) -> Span {
+        self.line += 1;
+        self.line_start = hi;
+
+        Span {
+            lo: lo,
+            hi: hi,
+            line: self.line,
+            col: 1,
+            col_utf16: 1,
+            end_line: self.line,
+            end_col: 1,
+            end_col_utf16: 1,
+        }
+    }
+
+    fn utf16_col(&self, offset: usize) -> usize {
+        self.text[self.line_start..offset].encode_utf16().count() + 1
+    }
 }
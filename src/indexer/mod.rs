@@ -1,5 +1,10 @@
 pub mod lexer;
 pub mod storage;
+pub mod symbols;
+pub mod persist;
+pub mod parallel;
+pub mod tree;
+pub mod outline;
 pub mod parser;
 pub mod gen;
 pub mod lang;
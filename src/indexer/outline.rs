@@ -0,0 +1,114 @@
+use indexer::lexer::Span;
+use indexer::parser::{Path, Tagged};
+
+/// Item of the file outline. Methods are nested in their `impl` blocks,
+/// variants in enums and items of inline modules in the modules.
+#[derive(Debug, Clone)]
+pub struct OutlineItem {
+    /// `Path::kind` of the definition or `impl`
+    pub kind: &'static str,
+    pub name: String,
+    pub line: usize,
+    pub col: usize,
+    /// Path of the definition, for `impl` blocks the path of the self type
+    pub path: Path,
+    /// Doc comment of the definition
+    pub doc: Option<String>,
+    pub children: Vec<OutlineItem>,
+}
+
+impl OutlineItem {
+    fn new(kind: &'static str, name: String, span: &Span, path: Path) -> OutlineItem {
+        OutlineItem {
+            kind: kind,
+            name: name,
+            line: span.line,
+            col: span.col,
+            path: path,
+            doc: None,
+            children: vec![],
+        }
+    }
+
+    /// Definitions with the path `parent` are nested in this item.
+    /// Methods of structs are nested in `impl` blocks rather than in the structs.
+    fn accepts(&self, parent: &Path) -> bool {
+        match self.kind {
+            "mod" | "fn" | "trait" | "enum" | "impl" => self.path == *parent,
+            _ => false,
+        }
+    }
+}
+
+/// Outline of the items in `parsed` in the source order, `docs` are doc comments of the definitions.
+pub fn outline(parsed: &[(Tagged, Span)], docs: &[(Span, String)]) -> Vec<OutlineItem> {
+    let mut items = vec![];
+    let mut open: Vec<OutlineItem> = vec![];
+
+    for &(ref tagged, ref span) in parsed {
+        match tagged {
+            &Tagged::Definition(ref use_context) => {
+                let path = &use_context.reference;
+                let parent = Path::path(path.path[..path.path.len().saturating_sub(1)].to_vec());
+
+                loop {
+                    let (accepts, inside) = match open.last() {
+                        Some(last) => (last.accepts(&parent), last.kind == "mod" && parent.starts_with(&last.path)),
+                        None => (parent.path.len() == 0, false),
+                    };
+
+                    if accepts {
+                        break;
+                    }
+                    if inside || open.len() == 0 {
+                        break;
+                    }
+                    close(&mut open, &mut items);
+                }
+
+                let mut item = OutlineItem::new(path.kind(), path.name(), span, path.clone());
+                item.doc = docs.iter().find(|&&(ref doc_span, _)| doc_span.lo == span.lo).map(|&(_, ref doc)| doc.clone());
+                open.push(item);
+            },
+            &Tagged::Implementation(ref impl_context) => {
+                while open.last().map(|last| last.kind != "mod").unwrap_or(false) {
+                    close(&mut open, &mut items);
+                }
+
+                // Self types of `impl` blocks in modules of the file start with the modules
+                let module = open.last().map(|last| last.path.clone()).unwrap_or(Path::path(vec![]));
+                let path = if impl_context.self_type.starts_with(&module) {
+                    impl_context.self_type.clone()
+                } else {
+                    let mut path = module.clone();
+                    path.path.extend(impl_context.self_type.path.iter().cloned());
+                    path
+                };
+                let self_type = Path::path(path.path[module.path.len()..].to_vec());
+                let name = if impl_context.is_inherent() {
+                    format!("{}", self_type)
+                } else {
+                    format!("{} for {}", impl_context.trait_ref, self_type)
+                };
+                open.push(OutlineItem::new("impl", name, span, path));
+            },
+            _ => {},
+        }
+    }
+
+    while open.len() > 0 {
+        close(&mut open, &mut items);
+    }
+    items
+}
+
+/// Moves the last open item into its parent.
+fn close(open: &mut Vec<OutlineItem>, items: &mut Vec<OutlineItem>) {
+    if let Some(item) = open.pop() {
+        match open.last_mut() {
+            Some(parent) => parent.children.push(item),
+            None => items.push(item),
+        }
+    }
+}
+
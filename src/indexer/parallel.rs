@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crossbeam;
+
+/// Applies `f` to every item on `jobs` threads. Results keep the order of the items,
+/// so the output doesn't depend on the number of threads.
+pub fn map<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+    where T: Sync, R: Send, F: Fn(&T) -> R + Sync {
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().map(|item| f(item)).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut done: Vec<(usize, R)> = crossbeam::scope(|scope| {
+        let workers: Vec<_> = (0..jobs).map(|_| {
+            scope.spawn(|| {
+                let mut done = vec![];
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    if i >= items.len() {
+                        break;
+                    }
+                    done.push((i, f(&items[i])));
+                }
+                done
+            })
+        }).collect();
+
+        workers.into_iter().flat_map(|worker| worker.join()).collect()
+    });
+
+    done.sort_by_key(|&(i, _)| i);
+    done.into_iter().map(|(_, res)| res).collect()
+}
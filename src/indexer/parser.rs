@@ -1,4 +1,6 @@
 use std::collections::vec_deque::VecDeque;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::intrinsics::discriminant_value;
 use std::cmp::{min, max};
 
@@ -29,6 +31,119 @@ impl Path {
             path: paths,
         }
     }
+
+    pub fn name(&self) -> String {
+        match self.path.last() {
+            Some(&(_, ref name)) => name.clone(),
+            None => String::new(),
+        }
+    }
+
+    /// Function defined in `impl` or `trait` block.
+    pub fn is_method(&self) -> bool {
+        let len = self.path.len();
+        if len < 2 {
+            return false;
+        }
+
+        match (&self.path[len - 2].0, &self.path[len - 1].0) {
+            (&Token::T_struct, &Token::T_fn) | (&Token::T_trait, &Token::T_fn) => true,
+            _ => false,
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.path.iter().map(|&(_, ref name)| name.clone()).collect()
+    }
+
+    /// Names of the leading inline modules, `a::b` for `mod a { mod b { fn c() } }`.
+    pub fn modules(&self) -> Vec<String> {
+        self.path.iter()
+            .take_while(|&&(ref token, _)| match token { &Token::T_mod => true, _ => false })
+            .map(|&(_, ref name)| name.clone())
+            .collect()
+    }
+
+    /// Kind of the last item, e.g. `fn` or `struct`.
+    pub fn kind(&self) -> &'static str {
+        match self.path.last() {
+            Some(&(Token::T_fn, _)) => "fn",
+            Some(&(Token::T_struct, _)) => "struct",
+            Some(&(Token::T_enum, _)) => "enum",
+            Some(&(Token::T_trait, _)) => "trait",
+            Some(&(Token::T_type, _)) => "type",
+            Some(&(Token::T_const, _)) => "const",
+            Some(&(Token::T_static, _)) => "static",
+            Some(&(Token::T_mod, _)) => "mod",
+            Some(&(Token::T_macro, _)) => "macro",
+            Some(&(Token::Ident(_), _)) => "variant",
+            _ => "",
+        }
+    }
+
+    pub fn starts_with(&self, prefix: &Path) -> bool {
+        prefix.path.len() <= self.path.len() && Path::path(self.path[..prefix.path.len()].to_vec()) == *prefix
+    }
+
+    pub fn join(&self, token: Token, name: String) -> Path {
+        let mut path = self.path.clone();
+        path.push((token, name));
+        Path::path(path)
+    }
+
+    /// Checks if the definition with this path can be referred as `reference`.
+    /// Leading modules and functions can be omitted: `fn foo` in `mod bar` is visible as `foo`,
+    /// but `Foo::new` can't be called as `new`.
+    pub fn matches(&self, reference: &Path) -> bool {
+        if reference.path.len() > self.path.len() {
+            return false;
+        }
+
+        let skip = self.path.len() - reference.path.len();
+        for i in 0..skip {
+            match self.path[i].0 {
+                Token::T_mod | Token::T_fn => {},
+                _ => { return false; },
+            }
+        }
+
+        for i in 0..reference.path.len() {
+            let (ref token, ref name) = self.path[skip + i];
+            if !namespace_eq(token, &reference.path[i].0) || *name != reference.path[i].1 {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Kinds in references are only guessed by the parser (`Foo::new()` gives `struct Foo` and `fn new`),
+/// so they are compared by namespace: `Token::Ident(..)` is an enum variant and fits both.
+pub fn namespace_eq(a: &Token, b: &Token) -> bool {
+    fn namespace(token: &Token) -> (bool, bool) {
+        match token {
+            &Token::T_struct | &Token::T_enum | &Token::T_trait | &Token::T_type => (true, false),
+            &Token::T_fn | &Token::T_const | &Token::T_static => (false, true),
+            &Token::Ident(_) => (true, true),
+            _ => (false, false),
+        }
+    }
+
+    if token_eq(a, b) {
+        return true;
+    }
+
+    let (a_type, a_value) = namespace(a);
+    let (b_type, b_value) = namespace(b);
+    a_type && b_type || a_value && b_value
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = self.path.iter().map(|&(_, ref name)| &name[..]).collect();
+        write!(f, "{}", names.join("::"))
+    }
 }
 
 impl PartialEq for Path {
@@ -47,6 +162,18 @@ impl PartialEq for Path {
     }
 }
 
+impl Eq for Path {}
+
+/// Kinds are hashed by discriminant only, as `token_eq` compares them.
+impl Hash for Path {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for &(ref token, ref name) in &self.path {
+            token_discriminant(token).hash(state);
+            name.hash(state);
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UseContext {
     pub reference: Path,
@@ -62,13 +189,150 @@ impl UseContext {
     }
 }
 
+/// `impl Trait for Type` block: the trait, the full path of the type
+/// and the methods defined inside.
+#[derive(Debug, Clone)]
+pub struct ImplContext {
+    /// Empty for inherent implementations
+    pub trait_ref: Path,
+    pub self_type: Path,
+    pub items: Vec<(String, Span)>,
+}
+
+impl ImplContext {
+    pub fn new(trait_ref: Path, self_type: Path) -> ImplContext {
+        ImplContext {
+            trait_ref: trait_ref,
+            self_type: self_type,
+            items: vec![],
+        }
+    }
+
+    /// `impl Foo` rather than `impl Trait for Foo`.
+    pub fn is_inherent(&self) -> bool {
+        self.trait_ref.path.len() == 0
+    }
+}
+
+/// Attribute of an item or, for `#![...]`, of the enclosing module.
+#[derive(Debug, Clone)]
+pub struct AttrContext {
+    /// Name of the attribute, e.g. `derive` or `cfg`
+    pub name: String,
+    pub inner: bool,
+    /// Traits of `#[derive(...)]` with the spans of their names
+    pub derives: Vec<(Path, Span)>,
+    pub used_from: Path,
+}
+
+impl AttrContext {
+    pub fn new(name: String, inner: bool, used_from: Path) -> AttrContext {
+        AttrContext {
+            name: name,
+            inner: inner,
+            derives: vec![],
+            used_from: used_from,
+        }
+    }
+}
+
+/// Path of the module `scope` is in, `module` is the module of the file.
+pub fn module_path(module: &[String], scope: &Path) -> Vec<String> {
+    let mut res = module.to_vec();
+    for &(ref token, ref name) in &scope.path {
+        if let &Token::T_mod = token {
+            res.push(name.clone());
+        }
+    }
+    res
+}
+
+/// Name from `use` declaration: the path as written (`self`, `super` and `crate` included)
+/// and the local name it's bound to. Intermediate modules of the path have no alias.
+#[derive(Debug, Clone)]
+pub struct ImportContext {
+    pub path: Vec<String>,
+    pub alias: Option<String>,
+    pub used_from: Path,
+}
+
+impl ImportContext {
+    pub fn new(path: Vec<String>, alias: Option<String>, used_from: Path) -> ImportContext {
+        ImportContext {
+            path: path,
+            alias: alias,
+            used_from: used_from,
+        }
+    }
+
+    pub fn is_glob(&self) -> bool {
+        match self.path.last() {
+            Some(name) => name == "*",
+            None => false,
+        }
+    }
+
+    /// Path from the crate root (of the module itself for globs), `module` is the module of the file.
+    pub fn absolute(&self, module: &[String]) -> Vec<String> {
+        let mut res = module_path(module, &self.used_from);
+        let mut skip = 0;
+
+        match self.path.get(0).map(|name| &name[..]) {
+            Some("self") => {
+                skip = 1;
+            },
+            Some("super") => {
+                while skip < self.path.len() && self.path[skip] == "super" {
+                    res.pop();
+                    skip += 1;
+                }
+            },
+            Some("crate") => {
+                res.clear();
+                skip = 1;
+            },
+            _ => {
+                res.clear();
+            },
+        }
+
+        res.extend(self.path[skip..].iter().cloned());
+        if self.is_glob() {
+            res.pop();
+        }
+        res
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Tagged {
     Definition(UseContext),
     Calling(UseContext),
+    MethodCall(UseContext),
+    /// `name!(...)`, `name![...]` or `name!{...}`
+    MacroCall(UseContext),
+    /// Type of a variable, an argument or a return value
+    TypeRef(UseContext),
+    /// Field access, `.name` without the call
+    Field(String),
+    /// Variable bound by `let`
+    Local(String),
+    Import(ImportContext),
+    Implementation(ImplContext),
     Whitespace(WhitespaceType),
     Comment,
+    DocComment,
+    /// Any string literal
     QuotedString,
+    /// Character or byte literal
+    Char,
+    Number,
+    Lifetime,
+    /// Arithmetic, comparison, logical and assignment operators, `->`, `=>`, `?` and ranges.
+    /// Not `<` and `>` of generics, `!` of macro calls or `&` and `*` starting types
+    Operator,
+    /// Whole `#[...]` or `#![...]`
+    Attribute(AttrContext),
     Keyword(Token),
     Eof,
 }
@@ -126,13 +390,15 @@ impl<'a> FuzzyRule<'a> for FuzzyTokenRule<'a> {
     }
 }
 
+pub fn token_discriminant(token: &Token) -> u64 {
+    unsafe { discriminant_value(token) }
+}
+
 pub fn token_eq(a: &Token, b: &Token) -> bool {
-    unsafe {
-        let x = discriminant_value(a);
-        let y = discriminant_value(b);
-        //println!("A {}, {}, {:?} {:?}", x, y, a, b);
-        return x == y;
-    }
+    let x = token_discriminant(a);
+    let y = token_discriminant(b);
+    //println!("A {}, {}, {:?} {:?}", x, y, a, b);
+    return x == y;
 }
 
 pub fn match_tokens<'a>(rule_tokens: &[Token], tokens: &VecDeque<(&'a Token, &'a Span)>) -> FuzzyRuleState {
@@ -215,28 +481,37 @@ impl<'a> FuzzyParser<'a> {
         self.cache.push_back(lex);
         //println!("{:?}", self.cache);
 
-        let mut new_queue_size = 1;
-
-        for rule in &mut self.rules {
-            //let () = rule;
-            let res = rule.match_tokens(&self.cache);
-            //println!("R: {:?}", res);
-            match res {
-                FuzzyRuleState::NotMatches => {},
-                FuzzyRuleState::Cont(max_size) => { new_queue_size = max(max_size, new_queue_size); },
-                FuzzyRuleState::Ready(tokens_eaten, tagged) => {
-                    //println!("Matched! {:?}, {}", self.cache, tokens_eaten);
-                    for _ in 0..tokens_eaten {
-                        self.cache.pop_front();
-                    }
-                    return tagged
-                },
+        loop {
+            let mut new_queue_size = 1;
+
+            for rule in &mut self.rules {
+                //let () = rule;
+                let res = rule.match_tokens(&self.cache);
+                //println!("R: {:?}", res);
+                match res {
+                    FuzzyRuleState::NotMatches => {},
+                    FuzzyRuleState::Cont(max_size) => { new_queue_size = max(max_size, new_queue_size); },
+                    FuzzyRuleState::Ready(tokens_eaten, tagged) => {
+                        //println!("Matched! {:?}, {}", self.cache, tokens_eaten);
+                        for _ in 0..tokens_eaten {
+                            self.cache.pop_front();
+                        }
+                        return tagged
+                    },
+                }
             }
-        }
 
-        self.current_size = new_queue_size;
+            self.current_size = new_queue_size;
 
-        vec![]
+            // Nothing starts with the first token, so the rest is matched without it
+            // rather than lagging behind the pushed tokens
+            if new_queue_size == 1 && self.cache.len() > 1 {
+                self.cache.pop_front();
+                continue;
+            }
+
+            return vec![];
+        }
     }
 }
 
@@ -251,7 +526,7 @@ impl<'a> Preprocessing<'a> for CPreprocessing {
         let (token, _) = lex;
 
         match token {
-            &Token::Whitespace(_) | &Token::Comment => None,
+            &Token::Whitespace(_) | &Token::Comment | &Token::DocComment | &Token::InnerDocComment => None,
             _ => Some(lex),
         }
     }
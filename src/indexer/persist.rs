@@ -0,0 +1,404 @@
+use std::io::prelude::*;
+use std::io;
+use std::sync::Arc;
+
+use indexer::lexer::{Token, Span, WhitespaceType};
+use indexer::parser::{Path, Tagged, UseContext, ImportContext, ImplContext, AttrContext, token_eq};
+use indexer::storage::PreparsedFile;
+
+/// Has to be increased on every change of the format, older indexes are rejected on load.
+pub const VERSION: u64 = 14;
+
+pub const MAGIC: &'static [u8] = b"satire-index";
+
+/// Binary encoding of the index: numbers are 8 bytes little endian,
+/// strings and lists are prefixed with their lengths.
+pub trait Persist: Sized {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()>;
+    fn load<R: Read>(input: &mut R) -> io::Result<Self>;
+}
+
+pub fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub fn save_header<W: Write>(out: &mut W) -> io::Result<()> {
+    try!(out.write_all(MAGIC));
+    VERSION.save(out)
+}
+
+pub fn load_header<R: Read>(input: &mut R) -> io::Result<()> {
+    let mut magic = vec![0; MAGIC.len()];
+    try!(input.read_exact(&mut magic));
+    if &magic[..] != MAGIC {
+        return Err(invalid("not an index file"));
+    }
+
+    let version = try!(u64::load(input));
+    if version != VERSION {
+        return Err(invalid(&format!("index version {} is not supported, expected {}", version, VERSION)));
+    }
+
+    Ok(())
+}
+
+impl Persist for u64 {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut bytes = [0u8; 8];
+        for i in 0..8 {
+            bytes[i] = (*self >> (i * 8)) as u8;
+        }
+        out.write_all(&bytes)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<u64> {
+        let mut bytes = [0u8; 8];
+        try!(input.read_exact(&mut bytes));
+        let mut value = 0;
+        for i in 0..8 {
+            value |= (bytes[i] as u64) << (i * 8);
+        }
+        Ok(value)
+    }
+}
+
+impl Persist for usize {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        (*self as u64).save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<usize> {
+        Ok(try!(u64::load(input)) as usize)
+    }
+}
+
+impl Persist for String {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(self.len().save(out));
+        out.write_all(self.as_bytes())
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<String> {
+        let len = try!(usize::load(input));
+        let mut bytes = vec![];
+        try!(input.take(len as u64).read_to_end(&mut bytes));
+        if bytes.len() != len {
+            return Err(invalid("unexpected end of index"));
+        }
+        String::from_utf8(bytes).map_err(|_| invalid("string is not UTF-8"))
+    }
+}
+
+impl Persist for bool {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        (*self as u64).save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<bool> {
+        Ok(try!(u64::load(input)) != 0)
+    }
+}
+
+impl<T: Persist> Persist for Vec<T> {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(self.len().save(out));
+        for item in self {
+            try!(item.save(out));
+        }
+        Ok(())
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<Vec<T>> {
+        let len = try!(usize::load(input));
+        let mut items = vec![];
+        for _ in 0..len {
+            items.push(try!(T::load(input)));
+        }
+        Ok(items)
+    }
+}
+
+impl<T: Persist> Persist for Option<T> {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        match self {
+            &Some(ref value) => {
+                try!(1usize.save(out));
+                value.save(out)
+            },
+            &None => 0usize.save(out),
+        }
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<Option<T>> {
+        match try!(usize::load(input)) {
+            0 => Ok(None),
+            _ => Ok(Some(try!(T::load(input)))),
+        }
+    }
+}
+
+impl<A: Persist, B: Persist> Persist for (A, B) {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(self.0.save(out));
+        self.1.save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<(A, B)> {
+        let a = try!(A::load(input));
+        let b = try!(B::load(input));
+        Ok((a, b))
+    }
+}
+
+/// Tokens without data, stored by their position here.
+const PLAIN_TOKENS: &'static [Token] = &[
+    Token::NoToken, Token::QuotedString, Token::RawString, Token::ByteString, Token::RawByteString,
+    Token::Char, Token::Byte, Token::Number, Token::Lifetime, Token::Pound,
+    Token::T_as, Token::T_break, Token::T_crate, Token::T_else, Token::T_enum, Token::T_extern,
+    Token::T_false, Token::T_fn, Token::T_for, Token::T_if, Token::T_impl, Token::T_in,
+    Token::T_let, Token::T_loop, Token::T_match, Token::T_mod, Token::T_move, Token::T_mut,
+    Token::T_pub, Token::T_ref, Token::T_return, Token::T_static, Token::T_self, Token::T_struct,
+    Token::T_super, Token::T_true, Token::T_trait, Token::T_type, Token::T_unsafe, Token::T_use,
+    Token::T_virtual, Token::T_while, Token::T_continue, Token::T_box, Token::T_const, Token::T_where,
+    Token::T_proc, Token::T_alignof, Token::T_become, Token::T_offsetof, Token::T_priv, Token::T_pure,
+    Token::T_sizeof, Token::T_typeof, Token::T_unsized, Token::T_yield, Token::T_do, Token::T_abstract,
+    Token::T_final, Token::T_override, Token::T_macro,
+    Token::LParen, Token::RParen, Token::LFigureParen, Token::RFigureParen, Token::Colon2, Token::Dot,
+    Token::LBracket, Token::RBracket, Token::Colon, Token::Semi, Token::Comma, Token::DotDot, Token::DotDotDot,
+    Token::DotDotEq, Token::RArrow, Token::FatArrow, Token::Bang, Token::Question, Token::At, Token::Dollar,
+    Token::Tilde, Token::Eq, Token::EqEq, Token::Ne, Token::Lt, Token::Le, Token::Gt, Token::Ge, Token::Shl,
+    Token::Shr, Token::Plus, Token::Minus, Token::Star, Token::Slash, Token::Percent, Token::Caret, Token::And,
+    Token::Or, Token::AndAnd, Token::OrOr, Token::PlusEq, Token::MinusEq, Token::StarEq, Token::SlashEq,
+    Token::PercentEq, Token::CaretEq, Token::AndEq, Token::OrEq, Token::ShlEq, Token::ShrEq,
+    Token::Comment, Token::DocComment, Token::InnerDocComment, Token::Eof, Token::Other,
+];
+
+const TOKEN_IDENT: usize = 0;
+const TOKEN_NEWLINE: usize = 1;
+const TOKEN_SPACES: usize = 2;
+const TOKEN_PLAIN: usize = 3;
+
+impl Persist for Token {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        match self {
+            &Token::Ident(ref name) => {
+                try!(TOKEN_IDENT.save(out));
+                name.save(out)
+            },
+            &Token::Whitespace(WhitespaceType::Newline) => TOKEN_NEWLINE.save(out),
+            &Token::Whitespace(WhitespaceType::Spaces) => TOKEN_SPACES.save(out),
+            _ => {
+                match PLAIN_TOKENS.iter().position(|plain| token_eq(plain, self)) {
+                    Some(index) => (TOKEN_PLAIN + index).save(out),
+                    None => Err(invalid(&format!("token {:?} can't be saved", self))),
+                }
+            },
+        }
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<Token> {
+        match try!(usize::load(input)) {
+            TOKEN_IDENT => Ok(Token::Ident(try!(String::load(input)))),
+            TOKEN_NEWLINE => Ok(Token::Whitespace(WhitespaceType::Newline)),
+            TOKEN_SPACES => Ok(Token::Whitespace(WhitespaceType::Spaces)),
+            code => {
+                match PLAIN_TOKENS.get(code - TOKEN_PLAIN) {
+                    Some(token) => Ok(token.clone()),
+                    None => Err(invalid("unknown token")),
+                }
+            },
+        }
+    }
+}
+
+impl Persist for WhitespaceType {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        Token::Whitespace(self.clone()).save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<WhitespaceType> {
+        match try!(Token::load(input)) {
+            Token::Whitespace(whitespace) => Ok(whitespace),
+            _ => Err(invalid("whitespace expected")),
+        }
+    }
+}
+
+impl Persist for Span {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(self.lo.save(out));
+        try!(self.hi.save(out));
+        try!(self.line.save(out));
+        try!(self.col.save(out));
+        try!(self.col_utf16.save(out));
+        try!(self.end_line.save(out));
+        try!(self.end_col.save(out));
+        self.end_col_utf16.save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<Span> {
+        Ok(Span {
+            lo: try!(usize::load(input)),
+            hi: try!(usize::load(input)),
+            line: try!(usize::load(input)),
+            col: try!(usize::load(input)),
+            col_utf16: try!(usize::load(input)),
+            end_line: try!(usize::load(input)),
+            end_col: try!(usize::load(input)),
+            end_col_utf16: try!(usize::load(input)),
+        })
+    }
+}
+
+impl Persist for Path {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        self.path.save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<Path> {
+        Ok(Path::path(try!(Vec::load(input))))
+    }
+}
+
+impl Persist for UseContext {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(self.reference.save(out));
+        self.used_from.save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<UseContext> {
+        let reference = try!(Path::load(input));
+        let used_from = try!(Path::load(input));
+        Ok(UseContext::new(reference, used_from))
+    }
+}
+
+impl Persist for ImportContext {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(self.path.save(out));
+        try!(self.alias.save(out));
+        self.used_from.save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<ImportContext> {
+        let path = try!(Vec::load(input));
+        let alias = try!(Option::load(input));
+        let used_from = try!(Path::load(input));
+        Ok(ImportContext::new(path, alias, used_from))
+    }
+}
+
+impl Persist for ImplContext {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(self.trait_ref.save(out));
+        try!(self.self_type.save(out));
+        self.items.save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<ImplContext> {
+        let mut context = ImplContext::new(try!(Path::load(input)), try!(Path::load(input)));
+        context.items = try!(Vec::load(input));
+        Ok(context)
+    }
+}
+
+impl Persist for AttrContext {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(self.name.save(out));
+        try!(self.inner.save(out));
+        try!(self.derives.save(out));
+        self.used_from.save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<AttrContext> {
+        let mut context = AttrContext::new(try!(String::load(input)), try!(bool::load(input)), Path::path(vec![]));
+        context.derives = try!(Vec::load(input));
+        context.used_from = try!(Path::load(input));
+        Ok(context)
+    }
+}
+
+impl Persist for Tagged {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        match self {
+            &Tagged::Definition(ref context) => { try!(0usize.save(out)); context.save(out) },
+            &Tagged::Calling(ref context) => { try!(1usize.save(out)); context.save(out) },
+            &Tagged::MethodCall(ref context) => { try!(2usize.save(out)); context.save(out) },
+            &Tagged::Import(ref context) => { try!(3usize.save(out)); context.save(out) },
+            &Tagged::Implementation(ref context) => { try!(4usize.save(out)); context.save(out) },
+            &Tagged::Whitespace(ref whitespace) => { try!(5usize.save(out)); whitespace.save(out) },
+            &Tagged::Comment => 6usize.save(out),
+            &Tagged::QuotedString => 7usize.save(out),
+            &Tagged::Keyword(ref token) => { try!(8usize.save(out)); token.save(out) },
+            &Tagged::Eof => 9usize.save(out),
+            &Tagged::Char => 10usize.save(out),
+            &Tagged::Number => 11usize.save(out),
+            &Tagged::Lifetime => 12usize.save(out),
+            &Tagged::Attribute(ref context) => { try!(13usize.save(out)); context.save(out) },
+            &Tagged::DocComment => 14usize.save(out),
+            &Tagged::MacroCall(ref context) => { try!(15usize.save(out)); context.save(out) },
+            &Tagged::Operator => 16usize.save(out),
+            &Tagged::Field(ref name) => { try!(17usize.save(out)); name.save(out) },
+            &Tagged::Local(ref name) => { try!(18usize.save(out)); name.save(out) },
+            &Tagged::TypeRef(ref context) => { try!(19usize.save(out)); context.save(out) },
+        }
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<Tagged> {
+        Ok(match try!(usize::load(input)) {
+            0 => Tagged::Definition(try!(UseContext::load(input))),
+            1 => Tagged::Calling(try!(UseContext::load(input))),
+            2 => Tagged::MethodCall(try!(UseContext::load(input))),
+            3 => Tagged::Import(try!(ImportContext::load(input))),
+            4 => Tagged::Implementation(try!(ImplContext::load(input))),
+            5 => Tagged::Whitespace(try!(WhitespaceType::load(input))),
+            6 => Tagged::Comment,
+            7 => Tagged::QuotedString,
+            8 => Tagged::Keyword(try!(Token::load(input))),
+            9 => Tagged::Eof,
+            10 => Tagged::Char,
+            11 => Tagged::Number,
+            12 => Tagged::Lifetime,
+            13 => Tagged::Attribute(try!(AttrContext::load(input))),
+            14 => Tagged::DocComment,
+            15 => Tagged::MacroCall(try!(UseContext::load(input))),
+            16 => Tagged::Operator,
+            17 => Tagged::Field(try!(String::load(input))),
+            18 => Tagged::Local(try!(String::load(input))),
+            19 => Tagged::TypeRef(try!(UseContext::load(input))),
+            _ => { return Err(invalid("unknown tag")); },
+        })
+    }
+}
+
+impl Persist for PreparsedFile {
+    fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(self.file.save(out));
+        try!(self.content.save(out));
+        try!(self.hash.save(out));
+        try!(self.syntax.save(out));
+        try!(self.parsed.save(out));
+        try!(self.modules.save(out));
+        try!(self.module.save(out));
+        try!(self.docs.save(out));
+        self.module_doc.save(out)
+    }
+
+    fn load<R: Read>(input: &mut R) -> io::Result<PreparsedFile> {
+        let file = try!(String::load(input));
+        let content = Arc::new(try!(String::load(input)));
+        let hash = try!(u64::load(input));
+        let syntax = try!(Vec::load(input));
+        let parsed = try!(Vec::load(input));
+        let modules = try!(Vec::load(input));
+
+        let mut preparsed = PreparsedFile::new(file, content, syntax, parsed, modules);
+        if preparsed.hash != hash {
+            return Err(invalid(&format!("content of {} doesn't match its hash", preparsed.file)));
+        }
+        preparsed.module = try!(Vec::load(input));
+        preparsed.docs = try!(Vec::load(input));
+        preparsed.module_doc = try!(Option::load(input));
+        Ok(preparsed)
+    }
+}
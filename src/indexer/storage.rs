@@ -5,19 +5,27 @@ use std::io;
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::io::BufWriter;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::mem;
+use std::time::UNIX_EPOCH;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::collections::vec_deque::VecDeque;
 
 use indexer::parser;
-use indexer::parser::{Tagged, CommonParser};
+use indexer::parser::{Tagged, CommonParser, UseContext, ImportContext};
 use indexer::lang::rust::RustParser;
 use indexer::lexer::Span;
-use indexer::gen;
+use indexer::gen::{self, GenConfig};
+use indexer::symbols::SymbolTable;
+use indexer::persist::{self, Persist};
+use indexer::parallel;
+use indexer::tree::{TreeDir, TreeFile};
+use indexer::outline;
 
 pub struct SourceFile {
     pub filename: String,
-    pub content: Rc<String>,
+    pub content: Arc<String>,
 }
 
 impl SourceFile {
@@ -29,36 +37,57 @@ impl SourceFile {
 
         SourceFile {
             filename: filename.clone(),
-            content: Rc::new(content),
+            content: Arc::new(content),
+        }
+    }
+
+    /// Modification time in nanoseconds, 0 if it's unknown.
+    pub fn modified(filename: &str) -> u64 {
+        let modified = fs::metadata(filename).and_then(|metadata| metadata.modified()).ok();
+        match modified.and_then(|time| time.duration_since(UNIX_EPOCH).ok()) {
+            Some(duration) => duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64,
+            None => 0,
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct FileSource {
     pub file: String,
     pub line: usize,
+    /// Byte column of the identifier, from 1
+    pub col: usize,
 }
 
 #[derive(Clone, Debug)]
 pub struct Info {
     pub refs: Vec<FileSource>,
+    pub impls: Vec<FileSource>,
+    pub candidates: Vec<(parser::Path, FileSource)>,
+    pub usages: Vec<FileSource>,
+    /// Doc comment of the definition or of the first referred one
+    pub doc: Option<String>,
 }
 
 impl fmt::Debug for FileSource {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}:{}", self.file, self.line)
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
     }
 }
 
 pub struct Index<'a> {
     pub set: Vec<&'a PreparsedFile>,
+    pub symbols: &'a SymbolTable,
+    /// Calling sites of every definition
+    pub references: HashMap<FileSource, Vec<FileSource>>,
 }
 
 impl<'a> Index<'a> {
-    pub fn new() -> Index<'a> {
+    pub fn new(symbols: &'a SymbolTable) -> Index<'a> {
         Index {
             set: vec![],
+            symbols: symbols,
+            references: HashMap::new(),
         }
     }
 
@@ -66,125 +95,609 @@ impl<'a> Index<'a> {
         self.set.push(preparsed);
     }
 
-    pub fn find(&self, path: &parser::Path) -> Vec<FileSource> {
+    /// Resolves calls of all files, has to be done after all files are added.
+    pub fn build_references(&mut self, jobs: usize) {
+        let resolved = parallel::map(&self.set, jobs, |preparsed| {
+            let imports = preparsed.imports();
+            let mut resolved = vec![];
+
+            for &(ref tagged, ref span) in &preparsed.parsed {
+                let definitions = match tagged {
+                    &Tagged::Calling(ref use_context) => preparsed.resolve(use_context, &imports, self),
+                    &Tagged::MacroCall(ref use_context) => self.find(&use_context.reference),
+                    _ => { continue; },
+                };
+
+                let usage = FileSource{
+                    file: preparsed.file.clone(),
+                    line: span.line,
+                    col: span.col,
+                };
+                for definition in definitions {
+                    resolved.push((definition, usage.clone()));
+                }
+            }
+
+            resolved
+        });
+
+        let mut references: HashMap<FileSource, Vec<FileSource>> = HashMap::new();
+        for (definition, usage) in resolved.into_iter().flat_map(|resolved| resolved) {
+            references.entry(definition).or_insert(vec![]).push(usage);
+        }
+
+        for (_, usages) in references.iter_mut() {
+            usages.sort();
+            usages.dedup();
+        }
+
+        self.references = references;
+    }
+
+    /// Calling sites of definitions which can be referred as `path`, see `Path::matches`.
+    pub fn references(&self, path: &parser::Path) -> Vec<FileSource> {
         let mut found = vec![];
 
-        for preparsed in &self.set {
-            found.append(&mut preparsed.find(path));
+        for definition in self.find(path) {
+            if let Some(usages) = self.references.get(&definition) {
+                found.extend(usages.iter().cloned());
+            }
         }
 
         found
     }
+
+    pub fn find(&self, path: &parser::Path) -> Vec<FileSource> {
+        self.symbols.find(path)
+    }
+
+    pub fn implementations(&self, path: &parser::Path) -> Vec<FileSource> {
+        self.symbols.implementations(path)
+    }
+
+    pub fn doc(&self, source: &FileSource) -> Option<String> {
+        self.symbols.docs.get(source).cloned()
+    }
+
+    /// Finds definitions and module files by path from the crate root.
+    pub fn find_absolute(&self, names: &[String]) -> Vec<FileSource> {
+        self.symbols.find_absolute(names)
+    }
+
+    /// `use` paths can start with the name of the crate itself (`extern crate satire; use satire::indexer;`),
+    /// so they are also tried without the first name.
+    pub fn find_import(&self, names: &[String]) -> Vec<FileSource> {
+        let found = self.find_absolute(names);
+        if found.len() == 0 && names.len() > 1 {
+            return self.find_absolute(&names[1..]);
+        }
+        found
+    }
+
+    /// Finds methods with given name for the call from `file` in `module`.
+    /// Candidates from the same file go first, then from the closest modules:
+    /// `a::b` is closer to `a::c` than to `d`.
+    pub fn methods(&self, name: &str, file: &str, module: &[String]) -> Vec<(parser::Path, FileSource)> {
+        let mut found = self.symbols.methods(name);
+
+        found.sort_by_key(|symbol| {
+            let common = symbol.full_module().iter().zip(module).take_while(|&(a, b)| a == b).count();
+            (symbol.source.file != file, module.len() - common)
+        });
+
+        found.into_iter().map(|symbol| (symbol.path.clone(), symbol.source.clone())).collect()
+    }
 }
 
 pub struct ParsedFile {
     pub file: String,
-    pub content: Rc<String>,
+    pub content: Arc<String>,
     pub preparsed: PreparsedFile,
+    /// `SourceFile::modified` when the file was parsed
+    pub mtime: u64,
+}
+
+/// What `IndexBuilder::update_dir` and `IndexBuilder::gen_affected` have done.
+#[derive(Debug)]
+pub struct RebuildReport {
+    pub reparsed: Vec<String>,
+    pub reused: Vec<String>,
+    pub removed: Vec<String>,
+    /// Names defined or used in old and new versions of reparsed files,
+    /// pages of other files mentioning them are regenerated
+    pub names: HashSet<String>,
+    /// Files were added or removed or their modules have changed, every page is regenerated
+    pub full: bool,
+    /// Pages written by `IndexBuilder::gen_affected`
+    pub regenerated: Option<Vec<String>>,
+}
+
+impl RebuildReport {
+    pub fn new() -> RebuildReport {
+        RebuildReport {
+            reparsed: vec![],
+            reused: vec![],
+            removed: vec![],
+            names: HashSet::new(),
+            full: false,
+            regenerated: None,
+        }
+    }
+
+    pub fn affects(&self, parsed_file: &ParsedFile) -> bool {
+        self.full || self.reparsed.contains(&parsed_file.file) ||
+            parsed_file.preparsed.names().iter().any(|name| self.names.contains(name))
+    }
 }
 
+impl fmt::Display for RebuildReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(writeln!(f, "Reparsed {} files, reused {}, removed {}", self.reparsed.len(), self.reused.len(), self.removed.len()));
+        for file in &self.reparsed {
+            try!(writeln!(f, "  parsed {}", file));
+        }
+        for file in &self.removed {
+            try!(writeln!(f, "  removed {}", file));
+        }
+        if let Some(ref regenerated) = self.regenerated {
+            try!(writeln!(f, "Regenerated {} pages", regenerated.len()));
+            for file in regenerated {
+                try!(writeln!(f, "  generated {}", file));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Name of the file `IndexBuilder::save` writes into the index directory.
+pub const INDEX_FILE: &'static str = "satire.index";
+
 pub struct IndexBuilder {
     //pub index: Index<'a>,
     pub set: Vec<ParsedFile>,
     pub dir_files: HashMap<String, Vec<PathBuf>>,
+    pub symbols: SymbolTable,
+    /// Files of the previous run `add_file` can reuse, see `update_dir`
+    pub previous: HashMap<String, ParsedFile>,
+    pub report: RebuildReport,
+    /// Threads for parsing, deduction and page generation
+    pub jobs: usize,
+    pub config: GenConfig,
 }
 
 impl IndexBuilder {
     pub fn new() -> IndexBuilder {
+        IndexBuilder::with_config(GenConfig::new("", "web/"))
+    }
+
+    pub fn with_config(config: GenConfig) -> IndexBuilder {
         IndexBuilder {
             set: vec![],
             dir_files: HashMap::new(),
+            symbols: SymbolTable::new(),
+            previous: HashMap::new(),
+            report: RebuildReport::new(),
+            jobs: 1,
+            config: config,
         }
     }
 
-    pub fn build_dir(&mut self, root_dir: &str) {
-        self.add_dir_rec(root_dir, root_dir);
+    /// Indexes `root_dir`, it becomes the source root of the config.
+    pub fn build_dir(&mut self, root_dir: &str) -> io::Result<()> {
+        let root_dir = gen::dir_path(root_dir);
+        let files = try!(self.scan_dir(&root_dir));
+        self.build_files(&files, &root_dir);
+        Ok(())
     }
 
-    pub fn deduce(&self) -> Vec<DeducedFile> {
-        let mut index = Index::new();
-        for parsed_file in &self.set {
-            index.add(&parsed_file.preparsed);
+    /// Lists files of `root_dir` into `dir_files`, which is kept as it was if the directory can't be read.
+    fn scan_dir(&mut self, root_dir: &str) -> io::Result<Vec<PathBuf>> {
+        let dir_files = mem::replace(&mut self.dir_files, HashMap::new());
+        let mut files = vec![];
+        if let Err(err) = self.add_dir_rec(root_dir, &mut files) {
+            self.dir_files = dir_files;
+            return Err(err);
         }
+        Ok(files)
+    }
+
+    fn build_files(&mut self, files: &[PathBuf], root_dir: &str) {
+        self.config.source_root = root_dir.to_string();
+        self.add_files(files, root_dir);
+        self.resolve_modules();
+        self.build_symbols();
+    }
 
-        let mut res = vec![];
+    /// Rebuilds the index of `root_dir` in place, files with the same modification time
+    /// or content as in the current index aren't parsed again.
+    /// The index isn't changed if `root_dir` can't be read.
+    pub fn update_dir(&mut self, root_dir: &str) -> io::Result<RebuildReport> {
+        let root_dir = gen::dir_path(root_dir);
+        let files = try!(self.scan_dir(&root_dir));
+
+        let previous = mem::replace(&mut self.set, vec![]);
+        let first = previous.len() == 0;
+        let mut modules = HashMap::new();
+        for parsed_file in previous {
+            modules.insert(parsed_file.file.clone(), parsed_file.preparsed.module.clone());
+            self.previous.insert(parsed_file.file.clone(), parsed_file);
+        }
+        self.report = RebuildReport::new();
+
+        self.build_files(&files, &root_dir);
+
+        let mut report = mem::replace(&mut self.report, RebuildReport::new());
+        for (file, parsed_file) in self.previous.drain() {
+            report.names.extend(parsed_file.preparsed.names());
+            report.removed.push(file);
+        }
+        report.removed.sort();
+
+        report.full = first || report.removed.len() > 0 || self.set.iter().any(|parsed_file| {
+            modules.get(&parsed_file.file) != Some(&parsed_file.preparsed.module)
+        });
+
+        Ok(report)
+    }
+
+    /// Maps `mod foo;` declarations to `foo.rs` and `foo/mod.rs` starting from `lib.rs` and `main.rs`.
+    /// Files not reachable from them get modules from their paths.
+    pub fn resolve_modules(&mut self) {
+        let mut by_file = HashMap::new();
+        for i in 0..self.set.len() {
+            by_file.insert(self.set[i].preparsed.file.clone(), i);
+        }
+
+        let mut modules: Vec<Option<Vec<String>>> = vec![None; self.set.len()];
+        let mut queue = VecDeque::new();
+
+        for i in 0..self.set.len() {
+            match Path::new(&self.set[i].preparsed.file).file_name().and_then(|name| name.to_str()) {
+                Some("lib.rs") | Some("main.rs") => {
+                    modules[i] = Some(vec![]);
+                    queue.push_back(i);
+                },
+                _ => {},
+            }
+        }
+
+        while let Some(i) = queue.pop_front() {
+            let module = modules[i].clone().unwrap();
+            let dir = module_dir(&self.set[i].preparsed.file);
+
+            for declared in &self.set[i].preparsed.modules {
+                let names = declared.names();
+                let (name, parents) = names.split_last().unwrap();
+
+                let mut sub_dir = dir.clone();
+                for parent in parents {
+                    sub_dir.push(parent);
+                }
+
+                let candidates = [sub_dir.join(format!("{}.rs", name)), sub_dir.join(name).join("mod.rs")];
+                for candidate in candidates.iter() {
+                    if let Some(&j) = candidate.to_str().and_then(|candidate| by_file.get(candidate)) {
+                        if modules[j].is_none() {
+                            let mut sub_module = module.clone();
+                            sub_module.extend(names.iter().cloned());
+                            modules[j] = Some(sub_module);
+                            queue.push_back(j);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        for i in 0..self.set.len() {
+            let module = match modules[i].take() {
+                Some(module) => module,
+                None => path_module(&self.set[i].preparsed.file),
+            };
+            self.set[i].preparsed.module = module;
+        }
+    }
+
+    /// Writes the source root and parsed files into `dir`.
+    ///
+    /// The symbol table isn't saved: it is derived from the parsed files by `build_symbols`
+    /// and rebuilt by `load`, so it can't get out of sync with them.
+    pub fn save(&self, dir: &str) -> io::Result<()> {
+        try!(fs::create_dir_all(dir));
+        let output = try!(File::create(Path::new(dir).join(INDEX_FILE)));
+        let mut writer = BufWriter::new(output);
+
+        try!(persist::save_header(&mut writer));
+        try!(self.config.source_root.save(&mut writer));
+
+        try!(self.set.len().save(&mut writer));
         for parsed_file in &self.set {
-            let deduced_file = parsed_file.preparsed.deduce(&index);
-            res.push(deduced_file);
+            try!(parsed_file.file.save(&mut writer));
+            try!(parsed_file.mtime.save(&mut writer));
+            try!(parsed_file.preparsed.save(&mut writer));
         }
 
-        res
+        let mut dirs: Vec<(&String, &Vec<PathBuf>)> = self.dir_files.iter().collect();
+        dirs.sort();
+        try!(dirs.len().save(&mut writer));
+        for (dir, files) in dirs {
+            try!(dir.save(&mut writer));
+            let files: Vec<String> = files.iter().map(|file| file.to_str().unwrap().to_string()).collect();
+            try!(files.save(&mut writer));
+        }
+
+        writer.flush()
     }
 
-    pub fn gen(&self) {
-        let mut index = Index::new();
+    pub fn load(dir: &str) -> io::Result<IndexBuilder> {
+        let input = try!(File::open(Path::new(dir).join(INDEX_FILE)));
+        let mut reader = BufReader::new(input);
+
+        try!(persist::load_header(&mut reader));
+
+        let mut index_builder = IndexBuilder::new();
+        index_builder.config.source_root = try!(String::load(&mut reader));
+
+        let len = try!(usize::load(&mut reader));
+        for _ in 0..len {
+            let file = try!(String::load(&mut reader));
+            let mtime = try!(u64::load(&mut reader));
+            let preparsed = try!(PreparsedFile::load(&mut reader));
+            index_builder.set.push(ParsedFile{
+                file: file,
+                content: preparsed.content.clone(),
+                preparsed: preparsed,
+                mtime: mtime,
+            });
+        }
+
+        let dirs: Vec<(String, Vec<String>)> = try!(Vec::load(&mut reader));
+        for (dir, files) in dirs {
+            index_builder.dir_files.insert(dir, files.iter().map(PathBuf::from).collect());
+        }
+
+        index_builder.build_symbols();
+        Ok(index_builder)
+    }
+
+    /// Has to be done after `resolve_modules`, definitions are keyed by modules of their files.
+    pub fn build_symbols(&mut self) {
+        let mut symbols = SymbolTable::new();
         for parsed_file in &self.set {
-            index.add(&parsed_file.preparsed);
+            symbols.add(&parsed_file.preparsed);
         }
+        self.symbols = symbols;
+    }
 
-        let mut deduced = vec![];
+    pub fn index(&self) -> Index {
+        let mut index = Index::new(&self.symbols);
         for parsed_file in &self.set {
-            let deduced_file = parsed_file.preparsed.deduce(&index);
-            deduced.push(deduced_file);
+            index.add(&parsed_file.preparsed);
         }
+        index.build_references(self.jobs);
+        index
+    }
 
-        for i in 0..deduced.len() {
-            let generated = deduced[i].gen();
+    pub fn deduce(&self) -> Vec<DeducedFile> {
+        let index = self.index();
+        parallel::map(&self.set, self.jobs, |parsed_file| parsed_file.preparsed.deduce(&index))
+    }
 
-            //let template = mustache::compile_path("web/code_template.html").unwrap();
-            let mut template_file = File::open("web/code_template.html").unwrap();
-            let mut template = String::new();
-            template_file.read_to_string(&mut template);
+    /// Renders pages of all files and directories, returns the number of the pages.
+    pub fn gen(&self) -> io::Result<usize> {
+        let (files, dirs) = try!(self.gen_pages(|_| true, |_| true));
+        Ok(files.len() + dirs)
+    }
 
-            let filepath = Path::new(&self.set[i].file);
-            let mut tree = String::new();
+    /// Regenerates pages `report` marks as affected and the missing ones, deletes pages of removed files.
+    /// Directory pages are regenerated when files under them were reparsed.
+    pub fn gen_affected(&self, report: &mut RebuildReport) -> io::Result<()> {
+        for file in &report.removed {
+            match fs::remove_file(self.config.page_file(self.config.relative(file))) {
+                Err(ref error) if error.kind() == io::ErrorKind::NotFound => {},
+                result => try!(result),
+            }
+        }
 
-            if let Some(parent) = filepath.parent() {
-                //println!("FF: {}", parent.to_str().unwrap());
-                tree.push_str("<ul>");
-                if let Some(value) = self.dir_files.get(parent.to_str().unwrap()) {
-                    for i in value {
-                        //tree.push_str(i.to_str().unwrap());
-                        let path = i.to_str().unwrap();
-                        tree.push_str(&format!("<li><a href=\"/{}.html\">{}</a></li>", path, path));
-                    }
-                }
-                tree.push_str("</ul>");
+        let regenerated = {
+            let affected: &RebuildReport = report;
+            let reparsed: Vec<&str> = affected.reparsed.iter().map(|file| self.config.relative(file)).collect();
+            try!(self.gen_pages(|parsed_file| {
+                affected.affects(parsed_file) || !self.config.page_file(&parsed_file.preparsed.file).exists()
+            }, |dir| {
+                affected.full || reparsed.iter().any(|file| file.starts_with(&dir.path)) ||
+                    !self.config.dir_page_file(&dir.path).exists()
+            })).0
+        };
+        report.regenerated = Some(regenerated);
+        Ok(())
+    }
+
+    /// Renders pages of files `filter` accepts on `jobs` threads and of directories `dir_filter` accepts,
+    /// returns names of the files and the number of the directory pages.
+    pub fn gen_pages<F, D>(&self, filter: F, dir_filter: D) -> io::Result<(Vec<String>, usize)>
+        where F: Fn(&ParsedFile) -> bool + Sync, D: Fn(&TreeDir) -> bool {
+        try!(self.copy_static());
+        let index = self.index();
+
+        let tree = self.tree();
+
+        let generated = parallel::map(&self.set, self.jobs, |parsed_file| -> io::Result<Option<String>> {
+            if !filter(parsed_file) {
+                return Ok(None);
             }
 
-            template = template.replace("{{tree}}", &tree);
+            try!(self.gen_page(parsed_file, &index));
+            Ok(Some(parsed_file.file.clone()))
+        });
 
-            let code = gen::to_string(deduced[i].content.clone(), &generated[..]);
-            template = template.replace("{{code}}", &code);
+        let mut generated_files = vec![];
+        for file in generated {
+            if let Some(file) = try!(file) {
+                generated_files.push(file);
+            }
+        }
+        let mut generated_dirs = 0;
+        for dir in tree.all_dirs() {
+            if dir_filter(dir) {
+                try!(self.gen_dir_page(dir));
+                generated_dirs += 1;
+            }
+        }
+        try!(self.gen_tree_index(&tree));
+        try!(self.gen_search_index());
+        Ok((generated_files, generated_dirs))
+    }
+
+    /// Writes the sidebar of all pages into `tree-index.js` of the output root.
+    pub fn gen_tree_index(&self, tree: &TreeDir) -> io::Result<()> {
+        let tree_index = gen::render_tree_index(&self.config.links(""), tree);
+        IndexBuilder::write_page(&Path::new(&self.config.output_root).join("tree-index.js"), &tree_index)
+    }
+
+    /// Writes definitions of all files into `search-index.js` of the output root.
+    pub fn gen_search_index(&self) -> io::Result<()> {
+        let search_index = gen::render_search_index(&self.symbols.all());
+        IndexBuilder::write_page(&Path::new(&self.config.output_root).join("search-index.js"), &search_index)
+    }
+
+    /// Tree of all files under the source root, indexed files come with their numbers of lines and definitions.
+    pub fn tree(&self) -> TreeDir {
+        let parsed: HashMap<&str, &ParsedFile> = self.set.iter().map(|parsed_file| (&parsed_file.file[..], parsed_file)).collect();
 
-            let title = format!("{}", self.set[i].file);
-            template = template.replace("{{title}}", &title);
+        let root_name = self.config.source_root.trim_right_matches('/').rsplit('/').next().unwrap_or("").to_string();
+        let mut root = TreeDir::new(root_name, String::new());
 
-            let dir = format!("web/{}", Path::new(&self.set[i].file).parent().unwrap().to_str().unwrap());
-            //println!("Create dir: {}", dir);
-            let res = fs::create_dir_all(dir);
-            let output = File::create(format!("web/{}.html", self.set[i].file)).unwrap();
-            let mut writer = BufWriter::new(output);
+        for files in self.dir_files.values() {
+            for file in files {
+                let file = file.to_str().unwrap();
+                let path = self.config.relative(file).to_string();
+                let name = path.rsplit('/').next().unwrap().to_string();
 
-            writer.write(template.as_bytes()).unwrap();
+                let tree_file = match parsed.get(file) {
+                    Some(parsed_file) => TreeFile {
+                        name: name,
+                        path: path,
+                        size: parsed_file.content.len() as u64,
+                        indexed: true,
+                        lines: parsed_file.content.lines().count(),
+                        symbols: parsed_file.preparsed.parsed.iter().filter(|&&(ref tagged, _)| {
+                            match tagged { &Tagged::Definition(_) => true, _ => false }
+                        }).count(),
+                    },
+                    None => TreeFile {
+                        name: name,
+                        path: path,
+                        size: fs::metadata(file).map(|metadata| metadata.len()).unwrap_or(0),
+                        indexed: false,
+                        lines: 0,
+                        symbols: 0,
+                    },
+                };
+                root.add(tree_file);
+            }
         }
+
+        root.sort();
+        root
+    }
+
+    fn read_template(&self) -> io::Result<String> {
+        let mut template_file = try!(File::open(&self.config.template));
+        let mut template = String::new();
+        try!(template_file.read_to_string(&mut template));
+        Ok(template)
+    }
+
+    fn write_page(page: &Path, content: &str) -> io::Result<()> {
+        if let Some(dir) = page.parent() {
+            try!(fs::create_dir_all(dir));
+        }
+        let output = try!(File::create(page));
+        let mut writer = BufWriter::new(output);
+
+        writer.write_all(content.as_bytes())
+    }
+
+    /// Writes `index.html` of the directory with the list of its contents.
+    pub fn gen_dir_page(&self, dir: &TreeDir) -> io::Result<()> {
+        let links = self.config.links(&dir.path);
+
+        let mut template = try!(self.read_template());
+        template = template.replace("{{tree}}", &gen::render_tree_container(&links, &dir.path));
+        let listing = gen::render_dir(&links, dir);
+        template = template.replace("{{code}}", &listing);
+        template = template.replace("{{content}}", &listing);
+        template = template.replace("{{outline}}", "");
+        let title = if dir.path.is_empty() { format!("{}/", dir.name) } else { dir.path.clone() };
+        template = template.replace("{{title}}", &title);
+        template = template.replace("{{root}}", &links.root);
+
+        IndexBuilder::write_page(&self.config.dir_page_file(&dir.path), &template)
+    }
+
+    /// Pages refer to assets relatively, so they are kept next to them.
+    pub fn copy_static(&self) -> io::Result<()> {
+        let from = Path::new(&self.config.static_dir);
+        let to = Path::new(&self.config.output_root).join("static");
+        if !from.exists() {
+            return Ok(());
+        }
+        if to.exists() && try!(fs::canonicalize(from)) == try!(fs::canonicalize(&to)) {
+            return Ok(());
+        }
+        copy_dir(from, &to)
+    }
+
+    pub fn gen_page(&self, parsed_file: &ParsedFile, index: &Index) -> io::Result<()> {
+        let deduced = parsed_file.preparsed.deduce(index);
+        let mut generated = deduced.gen();
+        if !self.config.operators {
+            generated.retain(|&(ref tagged, _, _)| match tagged { &Tagged::Operator => false, _ => true });
+        }
+
+        //let template = mustache::compile_path("web/code_template.html").unwrap();
+        let mut template = try!(self.read_template());
+
+        let relative = self.config.relative(&parsed_file.preparsed.file);
+        let links = self.config.links(&parsed_file.preparsed.file);
+
+        template = template.replace("{{tree}}", &gen::render_tree_container(&links, relative));
+
+        let code = gen::to_string(&links, deduced.content.clone(), &generated[..]);
+        template = template.replace("{{code}}", &code);
+        template = template.replace("{{content}}", &format!("<pre class=\"code\"><code>{}</code></pre>", code));
+        let outline = outline::outline(&parsed_file.preparsed.parsed, &parsed_file.preparsed.docs);
+        template = template.replace("{{outline}}", &format!("{}{}", gen::render_doc(&parsed_file.preparsed.module_doc), gen::render_outline(&outline)));
+
+        let title = format!("{}", parsed_file.preparsed.file);
+        template = template.replace("{{title}}", &title);
+        template = template.replace("{{root}}", &links.root);
+
+        IndexBuilder::write_page(&self.config.page_file(&parsed_file.preparsed.file), &template)
     }
 
-    pub fn add_dir_rec(&mut self, dir: &str, root_dir: &str) -> io::Result<()> {
+    /// Collects files of `dir` and its subdirectories in `all_files`, sorted by names.
+    pub fn add_dir_rec(&mut self, dir: &str, all_files: &mut Vec<PathBuf>) -> io::Result<()> {
         let mut files = vec![];
         if try!(fs::metadata(dir)).is_dir() {
+            let mut entries = vec![];
             for entry in try!(fs::read_dir(dir)) {
-                let entry = try!(entry);
-                if try!(fs::metadata(entry.path())).is_dir() {
-                    try!(self.add_dir_rec(&entry.path().to_str().unwrap(), root_dir));
+                entries.push(try!(entry).path());
+            }
+            entries.sort();
+
+            for path in entries {
+                if try!(fs::metadata(&path)).is_dir() {
+                    try!(self.add_dir_rec(&path.to_str().unwrap(), all_files));
                 } else {
-                    files.push(entry.path());
-                    try!(self.add_file(&entry.path(), root_dir));
+                    files.push(path.clone());
+                    all_files.push(path);
                 }
             }
         }
 
-        let add_dir = &dir[..dir.len() - 1];
+        let add_dir = dir.trim_right_matches('/');
         //println!("AA: {}", add_dir);
         self.dir_files.insert(add_dir.to_string(), files);
 
@@ -192,42 +705,122 @@ impl IndexBuilder {
     }
 
     pub fn add_file(&mut self, filepath: &PathBuf, root_dir: &str) -> io::Result<()> {
-        let file = filepath.to_str().unwrap();
-        //println!("F: {}", file);
+        self.add_files(&[filepath.clone()], root_dir);
+        Ok(())
+    }
 
-        let mut handle = true;
+    /// Parses Rust files on `jobs` threads, files with the same modification time
+    /// or content as in `previous` are reused. The order of `set` follows `filepaths`.
+    pub fn add_files(&mut self, filepaths: &[PathBuf], root_dir: &str) {
+        let files: Vec<(String, u64)> = filepaths.iter()
+            .filter(|filepath| filepath.extension().map_or(true, |file_ext| file_ext == "rs"))
+            .map(|filepath| {
+                let file = filepath.to_str().unwrap().to_string();
+                let mtime = SourceFile::modified(&file);
+                (file, mtime)
+            })
+            .collect();
 
-        if let Some(file_ext) = filepath.extension() {
-            if file_ext != "rs" {
-                handle = false;
-            }
-        }
+        let parsed = {
+            let previous = &self.previous;
+            parallel::map(&files, self.jobs, |&(ref file, mtime)| {
+                let previous_hash = match previous.get(file) {
+                    Some(parsed_file) if parsed_file.mtime == mtime => { return None; },
+                    Some(parsed_file) => Some(parsed_file.preparsed.hash),
+                    None => None,
+                };
+
+                let source = SourceFile::new(file.clone());
+                if previous_hash == Some(content_hash(&source.content)) {
+                    return None;
+                }
 
-        if handle {
-            let source = SourceFile::new(file.to_string().clone());
-            let mut parser = RustParser::new(file[root_dir.len()..].to_string(), source.content.clone());
-            let preparsed = parser.parse();
-            //println!("  f: {}", ctx.pars);
+                let mut parser = RustParser::new(file[root_dir.len()..].to_string(), source.content.clone());
+                Some(parser.parse())
+            })
+        };
+
+        for (&(ref file, mtime), preparsed) in files.iter().zip(parsed) {
+            let previous = self.previous.remove(file);
+            let preparsed = match preparsed {
+                Some(preparsed) => preparsed,
+                None => {
+                    self.reuse(previous.unwrap(), mtime);
+                    continue;
+                },
+            };
+
+            if let Some(parsed_file) = previous {
+                self.report.names.extend(parsed_file.preparsed.names());
+            }
+            self.report.names.extend(preparsed.names());
+            self.report.reparsed.push(file.clone());
             self.set.push(ParsedFile{
-                file: file.to_string().clone(),
-                content: source.content.clone(),
+                file: file.clone(),
+                content: preparsed.content.clone(),
                 preparsed: preparsed,
+                mtime: mtime,
             });
         }
+    }
 
-        Ok(())
+    fn reuse(&mut self, mut parsed_file: ParsedFile, mtime: u64) {
+        parsed_file.mtime = mtime;
+        self.report.reused.push(parsed_file.file.clone());
+        self.set.push(parsed_file);
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    try!(fs::create_dir_all(to));
+    for entry in try!(fs::read_dir(from)) {
+        let path = try!(entry).path();
+        let target = to.join(path.file_name().unwrap());
+        if try!(fs::metadata(&path)).is_dir() {
+            try!(copy_dir(&path, &target));
+        } else {
+            try!(fs::copy(&path, &target));
+        }
+    }
+    Ok(())
+}
+
+/// Directory with files of submodules: `foo/mod.rs` and `foo.rs` both keep them in `foo/`.
+fn module_dir(file: &str) -> PathBuf {
+    let path = Path::new(file);
+    let parent = path.parent().map(|parent| parent.to_path_buf()).unwrap_or(PathBuf::new());
+
+    match path.file_stem().and_then(|stem| stem.to_str()) {
+        Some("mod") | Some("lib") | Some("main") | None => parent,
+        Some(stem) => parent.join(stem),
+    }
+}
+
+fn path_module(file: &str) -> Vec<String> {
+    let dir = module_dir(file);
+    dir.components().filter_map(|component| component.as_os_str().to_str()).map(|name| name.to_string()).collect()
+}
+
+/// 64-bit FNV-1a of the content. Hashes are saved in the index, so unlike `DefaultHasher`
+/// the result must not change between Rust versions.
+pub fn content_hash(content: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in content.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
     }
+    hash
 }
 
 pub struct DeducedFile {
     pub file: String,
-    pub content: Rc<String>,
+    pub content: Arc<String>,
     pub synt: Vec<(Tagged, Span, Option<Box<Info>>)>,
     pub pars: Vec<(Tagged, Span, Option<Box<Info>>)>,
 }
 
 impl DeducedFile {
-    pub fn new(file: String, content: Rc<String>, synt: Vec<(Tagged, Span, Option<Box<Info>>)>, pars: Vec<(Tagged, Span, Option<Box<Info>>)>) -> DeducedFile {
+    pub fn new(file: String, content: Arc<String>, synt: Vec<(Tagged, Span, Option<Box<Info>>)>, pars: Vec<(Tagged, Span, Option<Box<Info>>)>) -> DeducedFile {
         DeducedFile {
             file: file,
             content: content,
@@ -280,21 +873,109 @@ impl DeducedFile {
 
 pub struct PreparsedFile {
     pub file: String,
-    pub content: Rc<String>,
+    pub content: Arc<String>,
     pub syntax: Vec<(Tagged, Span)>,
     pub parsed: Vec<(Tagged, Span)>,
+    /// Declarations of modules in other files (`mod foo;`)
+    pub modules: Vec<parser::Path>,
+    /// Module of the file from the crate root, known after `IndexBuilder::resolve_modules`
+    pub module: Vec<String>,
+    /// `content_hash` of the content
+    pub hash: u64,
+    /// Doc comments of definitions with the spans of their names
+    pub docs: Vec<(Span, String)>,
+    /// Inner doc comment of the file (`//!` at the top)
+    pub module_doc: Option<String>,
 }
 
 impl PreparsedFile {
-    pub fn new(file: String, content: Rc<String>, syntax: Vec<(Tagged, Span)>, parsed: Vec<(Tagged, Span)>) -> PreparsedFile {
+    pub fn new(file: String, content: Arc<String>, syntax: Vec<(Tagged, Span)>, parsed: Vec<(Tagged, Span)>, modules: Vec<parser::Path>) -> PreparsedFile {
+        let hash = content_hash(&content);
         PreparsedFile {
             file: file,
             content: content,
             syntax: syntax,
             parsed: parsed,
+            modules: modules,
+            module: vec![],
+            hash: hash,
+            docs: vec![],
+            module_doc: None,
         }
     }
 
+    /// All names the file defines or refers to.
+    pub fn names(&self) -> HashSet<String> {
+        let mut names = HashSet::new();
+        for &(ref tagged, _) in &self.parsed {
+            match tagged {
+                &Tagged::Definition(ref use_context) | &Tagged::Calling(ref use_context) |
+                &Tagged::MethodCall(ref use_context) | &Tagged::MacroCall(ref use_context) |
+                &Tagged::TypeRef(ref use_context) => {
+                    names.extend(use_context.reference.names());
+                },
+                &Tagged::Import(ref import) => {
+                    names.extend(import.path.iter().cloned());
+                },
+                &Tagged::Implementation(ref impl_context) => {
+                    names.extend(impl_context.trait_ref.names());
+                    names.extend(impl_context.self_type.names());
+                    names.extend(impl_context.items.iter().map(|&(ref item, _)| item.clone()));
+                },
+                _ => {},
+            }
+        }
+        names
+    }
+
+    pub fn imports(&self) -> Vec<&ImportContext> {
+        let mut imports = vec![];
+        for &(ref tagged, _) in &self.parsed {
+            match tagged {
+                &Tagged::Import(ref import) if import.alias.is_some() || import.is_glob() => {
+                    imports.push(import);
+                },
+                _ => {},
+            }
+        }
+        imports
+    }
+
+    /// Resolves the reference through the module it's used from and `use` declarations of the file.
+    /// Falls back to the search by name.
+    pub fn resolve(&self, use_context: &UseContext, imports: &[&ImportContext], index: &Index) -> Vec<FileSource> {
+        let names = use_context.reference.names();
+
+        let mut local = parser::module_path(&self.module, &use_context.used_from);
+        local.extend(names.iter().cloned());
+        let found = index.find_absolute(&local);
+        if found.len() > 0 {
+            return found;
+        }
+
+        for import in imports {
+            if !use_context.used_from.starts_with(&import.used_from) {
+                continue;
+            }
+
+            let mut target = import.absolute(&self.module);
+            if import.is_glob() {
+                target.extend(names.iter().cloned());
+            } else if import.alias.as_ref() == names.first() {
+                target.extend(names[1..].iter().cloned());
+            } else {
+                continue;
+            }
+
+            let found = index.find_import(&target);
+            if found.len() > 0 {
+                return found;
+            }
+        }
+
+        index.find(&use_context.reference)
+    }
+
     pub fn find(&self, path: &parser::Path) -> Vec<FileSource> {
         let mut found = vec![];
         for &(ref tagged, ref span) in &self.parsed {
@@ -302,11 +983,12 @@ impl PreparsedFile {
             match tagged {
                 &Tagged::Definition(ref use_context) => {
                     //println!("  l: {:?} {:?} {:?}", tagged, &use_context.reference, path);
-                    if use_context.reference == *path {
+                    if use_context.reference.matches(path) {
                         //println!("    matched: {:?}", tagged);
                         found.push(FileSource{
                             file: self.file.clone(),
                             line: span.line,
+                            col: span.col,
                         })
                     }
                 },
@@ -320,20 +1002,124 @@ impl PreparsedFile {
         let mut pars: Vec<(Tagged, Span, Option<Box<Info>>)> = vec![];
         let mut synt: Vec<(Tagged, Span, Option<Box<Info>>)> = vec![];
 
+        let imports = self.imports();
+
         for &(ref tagged, ref span) in &self.parsed {
             let mut info = None;
 
 
             match tagged {
-                &Tagged::Calling(ref use_context) => {
+                // Types are resolved as calls but aren't counted as usages
+                &Tagged::Calling(ref use_context) | &Tagged::TypeRef(ref use_context) => {
                     //println!("QQQ: {:?} {:?}", tagged, span);
-                    let refs = index.find(&use_context.reference);
+                    let refs = self.resolve(use_context, &imports, index);
                     if refs.len() > 0 {
                         //println!("  c: {:?} {:?}", tagged, span);
                         //println!("  f: {:?} {:?}", ftagged, fspan);
 
+                        let doc = index.doc(&refs[0]);
                         info = Some(Box::new(Info{
                             refs: refs,
+                            impls: vec![],
+                            candidates: vec![],
+                            usages: vec![],
+                            doc: doc,
+                        }));
+                    }
+                },
+                &Tagged::MacroCall(ref use_context) => {
+                    // `macro_rules!` are visible by names rather than by paths
+                    let refs = index.find(&use_context.reference);
+                    if refs.len() > 0 {
+                        let doc = index.doc(&refs[0]);
+                        info = Some(Box::new(Info{
+                            refs: refs,
+                            impls: vec![],
+                            candidates: vec![],
+                            usages: vec![],
+                            doc: doc,
+                        }));
+                    }
+                },
+                &Tagged::Import(ref import) => {
+                    let refs = index.find_import(&import.absolute(&self.module));
+                    if refs.len() > 0 {
+                        // Modules are found by `mod foo;` and by the file with the docs
+                        let doc = refs.iter().filter_map(|source| index.doc(source)).next();
+                        info = Some(Box::new(Info{
+                            refs: refs,
+                            impls: vec![],
+                            candidates: vec![],
+                            usages: vec![],
+                            doc: doc,
+                        }));
+                    }
+                },
+                &Tagged::MethodCall(ref use_context) => {
+                    let mut module = self.module.clone();
+                    module.extend(use_context.used_from.modules());
+                    let candidates = index.methods(&use_context.reference.name(), &self.file, &module);
+                    if candidates.len() > 0 {
+                        info = Some(Box::new(Info{
+                            refs: vec![],
+                            impls: vec![],
+                            candidates: candidates,
+                            usages: vec![],
+                            doc: None,
+                        }));
+                    }
+                },
+                &Tagged::Definition(ref use_context) => {
+                    let impls = index.implementations(&use_context.reference);
+                    let definition = FileSource{
+                        file: self.file.clone(),
+                        line: span.line,
+                        col: span.col,
+                    };
+                    let usages = match index.references.get(&definition) {
+                        Some(usages) => usages.clone(),
+                        None => vec![],
+                    };
+
+                    let doc = index.doc(&definition);
+
+                    if impls.len() > 0 || usages.len() > 0 || doc.is_some() {
+                        info = Some(Box::new(Info{
+                            refs: vec![],
+                            impls: impls,
+                            candidates: vec![],
+                            usages: usages,
+                            doc: doc,
+                        }));
+                    }
+                },
+                &Tagged::Implementation(ref impl_context) => {
+                    let implemented = if impl_context.is_inherent() { &impl_context.self_type } else { &impl_context.trait_ref };
+                    info = Some(Box::new(Info{
+                        refs: index.find(implemented),
+                        impls: vec![],
+                        candidates: vec![],
+                        usages: vec![],
+                        doc: None,
+                    }));
+                },
+                &Tagged::Attribute(ref attribute) => {
+                    // Derived traits with their definitions
+                    let mut candidates = vec![];
+                    for &(ref path, _) in &attribute.derives {
+                        let use_context = UseContext::new(path.clone(), attribute.used_from.clone());
+                        for source in self.resolve(&use_context, &imports, index) {
+                            candidates.push((path.clone(), source));
+                        }
+                    }
+
+                    if candidates.len() > 0 {
+                        info = Some(Box::new(Info{
+                            refs: vec![],
+                            impls: vec![],
+                            candidates: candidates,
+                            usages: vec![],
+                            doc: None,
                         }));
                     }
                 },
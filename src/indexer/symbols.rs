@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use indexer::lexer::Token;
+use indexer::parser::{Path, Tagged, ImplContext};
+use indexer::storage::{PreparsedFile, FileSource};
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub path: Path,
+    pub module: Vec<String>,
+    pub source: FileSource,
+}
+
+impl Symbol {
+    /// Path from the crate root, the module of the file included.
+    pub fn full_names(&self) -> Vec<String> {
+        let mut full = self.module.clone();
+        full.extend(self.path.names());
+        full
+    }
+
+    /// Module from the crate root the definition is in.
+    pub fn full_module(&self) -> Vec<String> {
+        let mut full = self.module.clone();
+        full.extend(self.path.modules());
+        full
+    }
+}
+
+/// Definitions and trait implementations of all files keyed by their last names and modules,
+/// so lookups don't scan every parsed file.
+pub struct SymbolTable {
+    pub names: HashMap<String, Vec<Symbol>>,
+    pub modules: HashMap<Vec<String>, Vec<FileSource>>,
+    pub traits: HashMap<String, Vec<(ImplContext, FileSource)>>,
+    /// Doc comments of definitions and module files
+    pub docs: HashMap<FileSource, String>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable {
+            names: HashMap::new(),
+            modules: HashMap::new(),
+            traits: HashMap::new(),
+            docs: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, preparsed: &PreparsedFile) {
+        let file_source = FileSource{
+            file: preparsed.file.clone(),
+            line: 1,
+            col: 1,
+        };
+        if preparsed.module.len() > 0 {
+            self.modules.entry(preparsed.module.clone()).or_insert(vec![]).push(file_source.clone());
+        }
+        if let Some(ref doc) = preparsed.module_doc {
+            self.docs.insert(file_source, doc.clone());
+        }
+
+        for &(ref tagged, ref span) in &preparsed.parsed {
+            let source = FileSource{
+                file: preparsed.file.clone(),
+                line: span.line,
+                col: span.col,
+            };
+
+            match tagged {
+                &Tagged::Definition(ref use_context) => {
+                    let path = &use_context.reference;
+                    self.names.entry(path.name()).or_insert(vec![]).push(Symbol{
+                        path: path.clone(),
+                        module: preparsed.module.clone(),
+                        source: source,
+                    });
+                },
+                &Tagged::Implementation(ref impl_context) if !impl_context.is_inherent() => {
+                    self.traits.entry(impl_context.trait_ref.name()).or_insert(vec![]).push((impl_context.clone(), source));
+                },
+                _ => {},
+            }
+        }
+
+        for &(ref span, ref doc) in &preparsed.docs {
+            self.docs.insert(FileSource{
+                file: preparsed.file.clone(),
+                line: span.line,
+                col: span.col,
+            }, doc.clone());
+        }
+    }
+
+    /// Definitions which can be referred as `path`, see `Path::matches`.
+    pub fn find(&self, path: &Path) -> Vec<FileSource> {
+        match self.names.get(&path.name()) {
+            Some(symbols) => {
+                symbols.iter()
+                    .filter(|symbol| symbol.path.matches(path))
+                    .map(|symbol| symbol.source.clone())
+                    .collect()
+            },
+            None => vec![],
+        }
+    }
+
+    /// All definitions sorted by names and places.
+    pub fn all(&self) -> Vec<&Symbol> {
+        let mut all: Vec<&Symbol> = self.names.values().flat_map(|symbols| symbols.iter()).collect();
+        all.sort_by(|a, b| (a.path.name(), &a.source).cmp(&(b.path.name(), &b.source)));
+        all
+    }
+
+    /// Definitions whose full paths (the module of the file included) end with `names`.
+    pub fn search(&self, names: &[String]) -> Vec<&Symbol> {
+        let symbols = match names.last().and_then(|name| self.names.get(name)) {
+            Some(symbols) => symbols,
+            None => { return vec![]; },
+        };
+
+        symbols.iter().filter(|symbol| symbol.full_names().ends_with(names)).collect()
+    }
+
+    /// Definitions and module files by path from the crate root.
+    pub fn find_absolute(&self, names: &[String]) -> Vec<FileSource> {
+        let mut found = match self.modules.get(names) {
+            Some(files) => files.clone(),
+            None => vec![],
+        };
+
+        let symbols = match names.last().and_then(|name| self.names.get(name)) {
+            Some(symbols) => symbols,
+            None => { return found; },
+        };
+
+        for symbol in symbols {
+            let len = symbol.module.len();
+            if names.len() == len + symbol.path.path.len() && &names[..len] == &symbol.module[..] &&
+                &symbol.path.names()[..] == &names[len..] {
+                found.push(symbol.source.clone());
+            }
+        }
+
+        found.sort();
+        found
+    }
+
+    /// Functions with given name defined in `impl` and `trait` blocks.
+    pub fn methods(&self, name: &str) -> Vec<&Symbol> {
+        match self.names.get(name) {
+            Some(symbols) => symbols.iter().filter(|symbol| symbol.path.is_method()).collect(),
+            None => vec![],
+        }
+    }
+
+    /// Implementations of the trait or of the trait method with given path.
+    pub fn implementations(&self, path: &Path) -> Vec<FileSource> {
+        let mut found = vec![];
+
+        let len = path.path.len();
+        let (trait_path, method) = match path.path.last() {
+            Some(&(Token::T_trait, _)) => (path.clone(), None),
+            Some(&(Token::T_fn, ref name)) if len >= 2 => {
+                match path.path[len - 2].0 {
+                    Token::T_trait => (Path::path(path.path[..len - 1].to_vec()), Some(name.clone())),
+                    _ => { return found; },
+                }
+            },
+            _ => { return found; },
+        };
+
+        let impls = match self.traits.get(&trait_path.name()) {
+            Some(impls) => impls,
+            None => { return found; },
+        };
+
+        for &(ref impl_context, ref source) in impls {
+            if !trait_path.matches(&impl_context.trait_ref) {
+                continue;
+            }
+
+            match method {
+                Some(ref name) => {
+                    for &(ref item, ref item_span) in &impl_context.items {
+                        if item == name {
+                            found.push(FileSource{
+                                file: source.file.clone(),
+                                line: item_span.line,
+                                col: item_span.col,
+                            });
+                        }
+                    }
+                },
+                None => {
+                    found.push(source.clone());
+                },
+            }
+        }
+
+        found
+    }
+}
@@ -0,0 +1,84 @@
+/// File of the source tree, paths are relative to the source root.
+#[derive(Debug, Clone)]
+pub struct TreeFile {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    /// Indexed files have pages, their lines and definitions are counted
+    pub indexed: bool,
+    pub lines: usize,
+    pub symbols: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct TreeDir {
+    pub name: String,
+    /// Path from the source root with the trailing slash, empty for the root itself
+    pub path: String,
+    pub dirs: Vec<TreeDir>,
+    pub files: Vec<TreeFile>,
+}
+
+impl TreeDir {
+    pub fn new(name: String, path: String) -> TreeDir {
+        TreeDir {
+            name: name,
+            path: path,
+            dirs: vec![],
+            files: vec![],
+        }
+    }
+
+    /// Adds the file with all directories on its way.
+    pub fn add(&mut self, file: TreeFile) {
+        let slash = file.path[self.path.len()..].find('/');
+        match slash {
+            Some(slash) => {
+                let end = self.path.len() + slash;
+                let name = file.path[self.path.len()..end].to_string();
+
+                let i = match self.dirs.iter().position(|dir| dir.name == name) {
+                    Some(i) => i,
+                    None => {
+                        let path = file.path[..end + 1].to_string();
+                        self.dirs.push(TreeDir::new(name, path));
+                        self.dirs.len() - 1
+                    },
+                };
+                self.dirs[i].add(file);
+            },
+            None => {
+                self.files.push(file);
+            },
+        }
+    }
+
+    pub fn sort(&mut self) {
+        self.dirs.sort_by(|a, b| a.name.cmp(&b.name));
+        self.files.sort_by(|a, b| a.name.cmp(&b.name));
+        for dir in &mut self.dirs {
+            dir.sort();
+        }
+    }
+
+    /// The directory and all its subdirectories.
+    pub fn all_dirs(&self) -> Vec<&TreeDir> {
+        let mut dirs = vec![self];
+        for dir in &self.dirs {
+            dirs.extend(dir.all_dirs());
+        }
+        dirs
+    }
+
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum::<u64>() + self.dirs.iter().map(|dir| dir.size()).sum::<u64>()
+    }
+
+    pub fn lines(&self) -> usize {
+        self.files.iter().map(|file| file.lines).sum::<usize>() + self.dirs.iter().map(|dir| dir.lines()).sum::<usize>()
+    }
+
+    pub fn symbols(&self) -> usize {
+        self.files.iter().map(|file| file.symbols).sum::<usize>() + self.dirs.iter().map(|dir| dir.symbols()).sum::<usize>()
+    }
+}
@@ -3,4 +3,7 @@
 #![feature(plugin)]
 #![plugin(plex)]
 
+extern crate crossbeam;
+extern crate unicode_xid;
+
 pub mod indexer;
@@ -1,11 +1,348 @@
 extern crate satire;
 
-use satire::indexer::storage::{IndexBuilder};
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+use satire::indexer::parser::Tagged;
+use satire::indexer::storage::{IndexBuilder, RebuildReport};
+use satire::indexer::gen::{self, GenConfig};
+
+const USAGE: &'static str = "Usage: satire <command> [options]
+
+Commands:
+    index <dir>       Parse Rust files of <dir> and save the index,
+                      files not changed since the previous run are reused
+    gen [<dir>]       Generate HTML pages from the saved index. With <dir> the index
+                      is updated first and only the affected pages are regenerated
+    query <symbol>    Print definitions, implementations and usages of the symbol,
+                      e.g. `new`, `Foo::new` or `items::Color`
+    stats             Print numbers of indexed files, definitions and references
+
+Options:
+    --root <dir>      Source root, the same as <dir> of `index` and `gen`
+    --index <dir>     Directory of the saved index [default: index/]
+    --out <dir>       Directory for generated pages [default: web/]
+    --base-url <url>  Absolute URL of the output directory, e.g. https://example.com/code/.
+                      Links are relative to pages without it
+    --template <file> Page template [default: web/code_template.html]
+    --operators       Highlight operators in generated pages
+    --jobs <n>        Number of threads [default: 1]
+    -h, --help        Print this message
+
+Exit codes:
+    0  Success
+    1  The symbol is not found
+    2  Wrong arguments
+    3  The index or pages can't be read or written";
+
+const EXIT_NOT_FOUND: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_IO: i32 = 3;
+
+struct Options {
+    command: Option<String>,
+    args: Vec<String>,
+    root_dir: Option<String>,
+    index_dir: String,
+    out_dir: String,
+    base_url: Option<String>,
+    template: String,
+    operators: bool,
+    jobs: usize,
+    help: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        command: None,
+        args: vec![],
+        root_dir: None,
+        index_dir: "index/".to_string(),
+        out_dir: "web/".to_string(),
+        base_url: None,
+        template: "web/code_template.html".to_string(),
+        operators: false,
+        jobs: 1,
+        help: false,
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        i += 1;
+
+        if arg == "-h" || arg == "--help" {
+            options.help = true;
+            continue;
+        }
+        if arg == "--operators" {
+            options.operators = true;
+            continue;
+        }
+
+        if !arg.starts_with("--") {
+            match options.command {
+                None => { options.command = Some(arg.clone()); },
+                Some(_) if options.args.is_empty() => { options.args.push(arg.clone()); },
+                Some(_) => { return Err(format!("unexpected argument {}", arg)); },
+            }
+            continue;
+        }
+
+        let value = match args.get(i) {
+            Some(value) => value.clone(),
+            None => { return Err(format!("{} requires a value", arg)); },
+        };
+        i += 1;
+
+        match &arg[..] {
+            "--root" => { options.root_dir = Some(value); },
+            "--index" => { options.index_dir = value; },
+            "--out" => { options.out_dir = value; },
+            "--base-url" => { options.base_url = Some(value); },
+            "--template" => { options.template = value; },
+            "--jobs" => {
+                options.jobs = match value.parse() {
+                    Ok(jobs) if jobs > 0 => jobs,
+                    _ => { return Err(format!("--jobs expects a positive number, got {}", value)); },
+                };
+            },
+            _ => { return Err(format!("unknown option {}", arg)); },
+        }
+    }
+
+    Ok(options)
+}
+
+fn error(message: &str) {
+    let _ = writeln!(io::stderr(), "{}", message);
+}
+
+fn configure(index_builder: &mut IndexBuilder, options: &Options) {
+    let mut config = GenConfig::new(&index_builder.config.source_root, &options.out_dir);
+    config.base_url = options.base_url.clone();
+    config.template = options.template.clone();
+    config.operators = options.operators;
+
+    index_builder.config = config;
+    index_builder.jobs = options.jobs;
+}
+
+fn open(options: &Options) -> Result<IndexBuilder, i32> {
+    match IndexBuilder::load(&options.index_dir) {
+        Ok(mut index_builder) => {
+            configure(&mut index_builder, options);
+            Ok(index_builder)
+        },
+        Err(err) => {
+            error(&format!("Can't load the index from {}: {}", options.index_dir, err));
+            Err(EXIT_IO)
+        },
+    }
+}
+
+fn update(options: &Options, root_dir: &str) -> Result<(IndexBuilder, RebuildReport), i32> {
+    let mut index_builder = match IndexBuilder::load(&options.index_dir) {
+        Ok(index_builder) => index_builder,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => IndexBuilder::new(),
+        Err(err) => {
+            error(&format!("Can't load the index from {}: {}", options.index_dir, err));
+            return Err(EXIT_IO);
+        },
+    };
+    configure(&mut index_builder, options);
+
+    let report = match index_builder.update_dir(root_dir) {
+        Ok(report) => report,
+        Err(err) => {
+            error(&format!("Can't read the sources in {}: {}", root_dir, err));
+            return Err(EXIT_IO);
+        },
+    };
+    if let Err(err) = index_builder.save(&options.index_dir) {
+        error(&format!("Can't save the index to {}: {}", options.index_dir, err));
+        return Err(EXIT_IO);
+    }
+
+    Ok((index_builder, report))
+}
+
+fn index(options: &Options, root_dir: &str) -> Result<(), i32> {
+    let (_, report) = try!(update(options, root_dir));
+    print!("{}", report);
+    Ok(())
+}
+
+fn gen(options: &Options, root_dir: Option<&String>) -> Result<(), i32> {
+    let res = match root_dir {
+        Some(root_dir) => {
+            let (index_builder, mut report) = try!(update(options, root_dir));
+            let res = index_builder.gen_affected(&mut report);
+            if res.is_ok() {
+                print!("{}", report);
+            }
+            res
+        },
+        None => {
+            let index_builder = try!(open(options));
+            index_builder.gen().map(|pages| println!("Generated {} pages", pages))
+        },
+    };
+
+    res.map_err(|err| {
+        error(&format!("Can't generate pages in {}: {}", options.out_dir, err));
+        EXIT_IO
+    })
+}
+
+fn query(options: &Options, symbol: &str) -> Result<(), i32> {
+    let index_builder = try!(open(options));
+    let index = index_builder.index();
+
+    let names: Vec<String> = symbol.split("::").map(|name| name.to_string()).collect();
+    let symbols = index_builder.symbols.search(&names);
+    if symbols.len() == 0 {
+        error(&format!("{} is not found", symbol));
+        return Err(EXIT_NOT_FOUND);
+    }
+
+    for symbol in symbols {
+        println!("{} {} {:?}", symbol.path.kind(), symbol.full_names().join("::"), symbol.source);
+
+        if let Some(doc) = index.doc(&symbol.source) {
+            println!("  {}", gen::doc_summary(&doc));
+        }
+
+        let implementations = index.implementations(&symbol.path);
+        if implementations.len() > 0 {
+            println!("  Implementations ({}):", implementations.len());
+            for implementation in implementations {
+                println!("    {:?}", implementation);
+            }
+        }
+
+        if let Some(usages) = index.references.get(&symbol.source) {
+            println!("  Usages ({}):", usages.len());
+            for usage in usages {
+                println!("    {:?}", usage);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn stats(options: &Options) -> Result<(), i32> {
+    let index_builder = try!(open(options));
+    let index = index_builder.index();
+
+    let mut lines = 0;
+    let mut definitions = 0;
+    let mut calls = 0;
+    let mut method_calls = 0;
+    let mut macro_calls = 0;
+    let mut type_refs = 0;
+    let mut imports = 0;
+    let mut implementations = 0;
+
+    for parsed_file in &index_builder.set {
+        lines += parsed_file.content.lines().count();
+
+        for &(ref tagged, _) in &parsed_file.preparsed.parsed {
+            match tagged {
+                &Tagged::Definition(_) => { definitions += 1; },
+                &Tagged::Calling(_) => { calls += 1; },
+                &Tagged::MethodCall(_) => { method_calls += 1; },
+                &Tagged::MacroCall(_) => { macro_calls += 1; },
+                &Tagged::TypeRef(_) => { type_refs += 1; },
+                &Tagged::Import(_) => { imports += 1; },
+                &Tagged::Implementation(ref impl_context) if !impl_context.is_inherent() => { implementations += 1; },
+                _ => {},
+            }
+        }
+    }
+
+    let references: usize = index.references.values().map(|usages| usages.len()).sum();
+
+    println!("Files: {}", index_builder.set.len());
+    println!("Lines: {}", lines);
+    println!("Definitions: {} ({} names)", definitions, index_builder.symbols.names.len());
+    println!("Calls: {} ({} resolved references)", calls, references);
+    println!("Method calls: {}", method_calls);
+    println!("Macro calls: {}", macro_calls);
+    println!("Type references: {}", type_refs);
+    println!("Imports: {}", imports);
+    println!("Trait implementations: {}", implementations);
+
+    Ok(())
+}
+
+fn run(options: &Options) -> Result<(), i32> {
+    let command = match options.command {
+        Some(ref command) => command,
+        None => {
+            error(USAGE);
+            return Err(EXIT_USAGE);
+        },
+    };
+
+    if options.root_dir.is_some() && command != "index" && command != "gen" {
+        error(&format!("{}: --root is only used by index and gen\n\n{}", command, USAGE));
+        return Err(EXIT_USAGE);
+    }
+
+    let root_dir = options.args.first().or(options.root_dir.as_ref());
+
+    match &command[..] {
+        "index" => {
+            match root_dir {
+                Some(root_dir) => index(options, root_dir),
+                None => {
+                    error(&format!("index: the source directory is missing\n\n{}", USAGE));
+                    Err(EXIT_USAGE)
+                },
+            }
+        },
+        "gen" => gen(options, root_dir),
+        "query" => {
+            match options.args.first() {
+                Some(symbol) => query(options, symbol),
+                None => {
+                    error(&format!("query: the symbol is missing\n\n{}", USAGE));
+                    Err(EXIT_USAGE)
+                },
+            }
+        },
+        "stats" if options.args.len() > 0 => {
+            error(&format!("stats: unexpected argument {}\n\n{}", options.args[0], USAGE));
+            Err(EXIT_USAGE)
+        },
+        "stats" => stats(options),
+        _ => {
+            error(&format!("Unknown command {}\n\n{}", command, USAGE));
+            Err(EXIT_USAGE)
+        },
+    }
+}
 
 fn main() {
-    let root_dir = "test/";
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            error(&format!("{}\n\n{}", err, USAGE));
+            process::exit(EXIT_USAGE);
+        },
+    };
+
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
 
-    let mut index_builder = IndexBuilder::new();
-    index_builder.build_dir(root_dir);
-    index_builder.gen();
+    if let Err(code) = run(&options) {
+        process::exit(code);
+    }
 }
@@ -0,0 +1,10 @@
+mod src;
+mod items;
+
+use src::*;
+use items::{self, Color, generic as identity};
+
+fn imported() {
+    println!("{:?}", Foo::new());
+    println!("{:?}", identity(items::LIMIT));
+}
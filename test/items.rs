@@ -0,0 +1,39 @@
+mod shapes {
+    pub struct Unit;
+
+    pub struct Pair(u32, u32);
+
+    pub struct Wrapper<'a> {
+        inner: &'a str,
+    }
+}
+
+enum Color {
+    Red,
+    #[allow(dead_code)]
+    Rgb(u8, u8, u8),
+    Named { name: String },
+}
+
+trait Paint {
+    type Output;
+
+    fn paint(&self, color: Color) -> Self::Output;
+}
+
+const LIMIT: usize = 10;
+static mut COUNTER: usize = 0;
+type Name = String;
+
+macro_rules! twice {
+    ($e:expr) => { $e; $e };
+}
+
+fn generic<T>(value: T) -> T {
+    value
+}
+
+fn uses() {
+    println!("{:?}", Color::Rgb(1, 2, 3));
+    println!("{:?}", generic(LIMIT));
+}
@@ -0,0 +1,10 @@
+const ZERO: *const u8 = 0 as *const u8;
+
+extern {
+    fn strlen(s: *const i8) -> usize;
+}
+
+fn raw(p: *mut u32, q: *const [u8]) -> *mut u32 {
+    let r = p as *const u32 as *mut u32;
+    r
+}
@@ -0,0 +1,124 @@
+extern crate satire;
+
+mod common;
+
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::process::Command;
+
+use satire::indexer::storage::INDEX_FILE;
+
+use common::write_tree;
+
+/// Runs the `satire` binary built next to the tests, returns its exit code and output.
+fn satire(args: &[&str]) -> (i32, String, String) {
+    // Tests are in `target/<profile>/deps/`, binaries in `target/<profile>/`
+    let exe = env::current_exe().unwrap();
+    let binary = exe.parent().unwrap().parent().unwrap().join(format!("satire{}", env::consts::EXE_SUFFIX));
+
+    let output = Command::new(&binary).args(args).output().unwrap();
+    (output.status.code().unwrap(), String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn wrong_arguments() {
+    let table: &[&[&str]] = &[
+        &[],
+        &["index"],
+        &["index", "a", "b"],
+        &["gen", "a", "b"],
+        &["query"],
+        &["query", "a", "b"],
+        &["stats", "a"],
+        &["compile"],
+        &["stats", "--jobs", "0"],
+        &["stats", "--index"],
+        &["stats", "--verbose", "1"],
+        &["stats", "--root", "a"],
+        &["query", "a", "--root", "a"],
+    ];
+
+    for args in table {
+        let (code, stdout, stderr) = satire(args);
+        assert_eq!(code, 2, "{:?}", args);
+        assert_eq!(stdout, "", "{:?}", args);
+        assert!(stderr.contains("Usage: satire"), "{:?}", args);
+    }
+}
+
+#[test]
+fn help() {
+    let (code, stdout, _) = satire(&["--help"]);
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("Usage: satire"));
+}
+
+#[test]
+fn commands() {
+    let root = write_tree("satire_cli", &[
+        ("template.html", "{{content}}"),
+        ("src/lib.rs", "fn area() {\n    square();\n}\n\n/// A square.\nfn square() {\n}\n"),
+    ]);
+    let path = |name: &str| root.join(name).to_str().unwrap().to_string();
+    let (src, index, out, template) = (path("src"), path("index"), path("out"), path("template.html"));
+
+    let (code, stdout, _) = satire(&["index", &src, "--index", &index]);
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("Reparsed 1 files, reused 0, removed 0\n"));
+
+    let (code, stdout, _) = satire(&["query", "square", "--index", &index]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("  A square."));
+    let (code, stdout, stderr) = satire(&["query", "circle", "--index", &index]);
+    assert_eq!((code, &stdout[..], &stderr[..]), (1, "", "circle is not found\n"));
+
+    let (code, stdout, _) = satire(&["gen", "--index", &index, "--out", &out, "--template", &template]);
+    // The page of the file and of its directory
+    assert_eq!((code, &stdout[..]), (0, "Generated 2 pages\n"));
+    assert!(root.join("out/lib.rs.html").exists());
+
+    // Nothing is reported when the pages can't be written
+    let (code, stdout, stderr) = satire(&["gen", "--index", &index, "--out", &out, "--template", &path("missing.html")]);
+    assert_eq!((code, &stdout[..]), (3, ""));
+    assert!(stderr.starts_with("Can't generate pages in "));
+    File::create(root.join("src/lib.rs")).unwrap().write_all(b"fn area() {\n}\n").unwrap();
+    let (code, stdout, _) = satire(&["gen", &src, "--index", &index, "--out", &out, "--template", &path("missing.html")]);
+    assert_eq!((code, &stdout[..]), (3, ""));
+
+    let (code, stdout, stderr) = satire(&["stats", "--index", &path("missing")]);
+    assert_eq!((code, &stdout[..]), (3, ""));
+    assert!(stderr.starts_with("Can't load the index from "));
+}
+
+#[test]
+fn unreadable_indexes_and_sources_are_errors() {
+    let root = write_tree("satire_cli_errors", &[
+        ("src/lib.rs", "fn area() {\n}\n"),
+    ]);
+    let path = |name: &str| root.join(name).to_str().unwrap().to_string();
+    let (src, index) = (path("src"), path("index"));
+    let read_index = || {
+        let mut saved = vec![];
+        File::open(root.join("index").join(INDEX_FILE)).unwrap().read_to_end(&mut saved).unwrap();
+        saved
+    };
+
+    assert_eq!(satire(&["index", &src, "--index", &index]).0, 0);
+    let saved = read_index();
+
+    // A mistyped source directory doesn't empty the index
+    let (code, stdout, stderr) = satire(&["index", &path("srcc"), "--index", &index]);
+    assert_eq!((code, &stdout[..]), (3, ""));
+    assert!(stderr.starts_with("Can't read the sources in "));
+    assert!(read_index() == saved);
+
+    // and a broken index isn't replaced with a new one
+    File::create(root.join("index").join(INDEX_FILE)).unwrap().write_all(b"garbage").unwrap();
+    for command in &["index", "gen"] {
+        let (code, stdout, stderr) = satire(&[command, &src, "--index", &index]);
+        assert_eq!((code, &stdout[..]), (3, ""));
+        assert!(stderr.starts_with("Can't load the index from "));
+        assert_eq!(read_index(), b"garbage");
+    }
+}
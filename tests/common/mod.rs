@@ -0,0 +1,18 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+/// Writes `files` into a fresh directory in the system temp dir.
+pub fn write_tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&root);
+
+    for &(file, content) in files {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(&path).unwrap().write_all(content.as_bytes()).unwrap();
+    }
+
+    root
+}
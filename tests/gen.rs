@@ -0,0 +1,436 @@
+extern crate satire;
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use satire::indexer::gen::{GenConfig, escape_attr};
+use satire::indexer::storage::IndexBuilder;
+
+use common::write_tree;
+
+fn read(path: PathBuf) -> String {
+    let mut content = String::new();
+    File::open(&path).unwrap().read_to_string(&mut content).unwrap();
+    content
+}
+
+fn gen_tree(name: &str, base_url: Option<&str>) -> (PathBuf, GenConfig) {
+    gen_files(name, &[
+        ("src/lib.rs", "mod shapes;\n\nfn area() {\n    shapes::square();\n}\n"),
+        ("src/shapes/mod.rs", "pub fn square() {\n}\n\npub struct Square;\n\nimpl Square {\n    pub fn side(&self) {\n    }\n}\n"),
+    ], |config| config.base_url = base_url.map(|base_url| base_url.to_string()))
+}
+
+/// Writes `files` next to `src/` with a minimal template (`files` can replace it), `configure` changes the config.
+fn files_config<F: FnOnce(&mut GenConfig)>(name: &str, files: &[(&str, &str)], configure: F) -> (PathBuf, GenConfig) {
+    let mut all = vec![
+        ("template.html", "<title>{{title}}</title><link href=\"{{root}}static/main.css\">{{tree}}{{content}}{{outline}}"),
+        ("static/main.css", "pre {}"),
+    ];
+    all.extend(files.iter().cloned());
+    let root = write_tree(name, &all);
+
+    let mut config = GenConfig::new(root.join("src").to_str().unwrap(), root.join("out").to_str().unwrap());
+    config.template = root.join("template.html").to_str().unwrap().to_string();
+    config.static_dir = root.join("static").to_str().unwrap().to_string();
+    configure(&mut config);
+
+    (root, config)
+}
+
+/// Generates pages of `files` in `src/`, see `files_config`.
+fn gen_files<F: FnOnce(&mut GenConfig)>(name: &str, files: &[(&str, &str)], configure: F) -> (PathBuf, GenConfig) {
+    let (root, config) = files_config(name, files, configure);
+
+    let mut index_builder = IndexBuilder::with_config(config.clone());
+    index_builder.build_dir(&config.source_root).unwrap();
+    index_builder.gen().unwrap();
+
+    (root, config)
+}
+
+#[test]
+fn pages_keep_paths_from_source_root() {
+    let (root, _) = gen_tree("satire_gen_paths", None);
+
+    assert!(root.join("out/lib.rs.html").exists());
+    assert!(root.join("out/shapes/mod.rs.html").exists());
+    assert!(root.join("out/static/main.css").exists());
+    assert!(!root.join("out/src").exists());
+}
+
+#[test]
+fn links_are_relative_to_page() {
+    let (root, _) = gen_tree("satire_gen_relative", None);
+
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(lib.contains("<title>lib.rs</title>"));
+    assert!(lib.contains("href=\"static/main.css\""));
+    assert!(lib.contains(&escape_attr("href='shapes/mod.rs.html#l1c8'")));
+    assert!(lib.contains("<script src='tree-index.js'></script>"));
+    assert!(!lib.contains("test/"));
+    assert!(!lib.contains(root.to_str().unwrap()));
+
+    let shapes = read(root.join("out/shapes/mod.rs.html"));
+    assert!(shapes.contains("href=\"../static/main.css\""));
+    assert!(shapes.contains(&escape_attr("href='../lib.rs.html#l4c13'")));
+}
+
+#[test]
+fn links_start_with_base_url() {
+    let (root, _) = gen_tree("satire_gen_base_url", Some("https://example.com/code/satire"));
+
+    let shapes = read(root.join("out/shapes/mod.rs.html"));
+    assert!(shapes.contains("href=\"https://example.com/code/satire/static/main.css\""));
+    assert!(shapes.contains(&escape_attr("href='https://example.com/code/satire/lib.rs.html#l4c13'")));
+
+    let tree = read(root.join("out/tree-index.js"));
+    assert!(tree.contains("<a href='https://example.com/code/satire/shapes/mod.rs.html' data-path='shapes/mod.rs'>"));
+
+    let (root, _) = gen_tree("satire_gen_base_url_quotes", Some("https://example.com/it's&more"));
+    let shapes = read(root.join("out/shapes/mod.rs.html"));
+    assert!(shapes.contains("<script src='https://example.com/it&#39;s&amp;more/tree-index.js'></script>\
+        <script src='https://example.com/it&#39;s&amp;more/static/js/tree.js'></script>"));
+}
+
+#[test]
+fn directories_have_index_pages() {
+    let (root, _) = gen_tree("satire_gen_dirs", None);
+
+    let index = read(root.join("out/index.html"));
+    assert!(index.contains("<title>src/</title>"));
+    assert!(index.contains("href='shapes/index.html'>shapes/</a>"));
+    assert!(index.contains("href='lib.rs.html'>lib.rs</a>"));
+
+    let shapes = read(root.join("out/shapes/index.html"));
+    assert!(shapes.contains("href=\"../static/main.css\""));
+    assert!(shapes.contains("href='../index.html'>..</a>"));
+    assert!(shapes.contains("href='../shapes/mod.rs.html'>mod.rs</a>"));
+}
+
+#[test]
+fn sidebar_is_shared_by_pages() {
+    let (root, _) = gen_tree("satire_gen_sidebar", None);
+
+    // The tree is rendered once with links from the output root
+    let tree = read(root.join("out/tree-index.js"));
+    assert!(tree.starts_with("var SOURCE_TREE = \"<ul class='tree'><li><a href='index.html' data-path=''>src/</a>"));
+    assert!(tree.contains("<li><details><summary><a href='shapes/index.html' data-path='shapes/'>shapes/</a></summary>"));
+    assert!(tree.contains("<a href='shapes/mod.rs.html' data-path='shapes/mod.rs'>mod.rs</a>"));
+
+    // and pages only tell where they are
+    let shapes = read(root.join("out/shapes/mod.rs.html"));
+    assert!(shapes.contains("<div class='tree-container' id='tree' data-root='../' data-current='shapes/mod.rs'></div>\
+        <script src='../tree-index.js'></script><script src='../static/js/tree.js'></script>"));
+    assert!(!shapes.contains("lib.rs.html'>lib.rs</a>"));
+
+    let dir = read(root.join("out/shapes/index.html"));
+    assert!(dir.contains("data-root='../' data-current='shapes/'"));
+}
+
+#[test]
+fn code_placeholder_of_older_templates() {
+    let (root, _) = gen_files("satire_gen_code_placeholder", &[
+        ("template.html", "<pre><code>{{code}}</code></pre>"),
+        ("src/lib.rs", "fn main() {\n}\n"),
+    ], |_| {});
+
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(lib.starts_with("<pre><code><a class=\"line\" name=\"l1\""));
+    assert!(lib.contains("<span class='fn def'>main</span>"));
+    assert!(read(root.join("out/index.html")).contains("href='lib.rs.html'>lib.rs</a>"));
+}
+
+#[test]
+fn search_index_lists_definitions() {
+    let (root, _) = gen_tree("satire_gen_search", None);
+
+    let search = read(root.join("out/search-index.js"));
+    assert!(search.starts_with("var SEARCH_INDEX = ["));
+    assert!(search.ends_with("];\n"));
+    assert!(search.contains("[\"area\",\"fn\",\"area\",\"lib.rs\",3,4]"));
+    assert!(search.contains("[\"square\",\"fn\",\"shapes::square\",\"shapes/mod.rs\",1,8]"));
+}
+
+#[test]
+fn outline_nests_methods_in_impls() {
+    let (root, _) = gen_tree("satire_gen_outline", None);
+
+    let shapes = read(root.join("out/shapes/mod.rs.html"));
+    assert!(shapes.contains("<ul class='outline'><li><span class='kind'>fn</span> <a href='#l1c8'>square</a></li>\
+        <li><span class='kind'>struct</span> <a href='#l4c12'>Square</a></li>\
+        <li><span class='kind'>impl</span> <a href='#l6c6'>Square</a><ul class='outline'>\
+        <li><span class='kind'>fn</span> <a href='#l7c12'>side</a></li></ul></li></ul>"));
+}
+
+#[test]
+fn trait_definitions_list_implementations() {
+    let (root, _) = gen_files("satire_gen_implementations", &[
+        ("src/lib.rs", "pub trait Shape {\n    fn area(&self) -> u32;\n}\n\nmod shapes {\n    struct Square;\n\n    impl Shape for Square {\n        fn area(&self) -> u32 {\n            1\n        }\n    }\n}\n\nstruct Y<'a>(&'a u32);\n\nimpl<'a> Shape for Y<'a> {\n    fn area(&self) -> u32 {\n        2\n    }\n}\n"),
+    ], |_| {});
+
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(lib.contains(&format!("title='Implementations (2)' data-content=\"{}",
+        escape_attr("<ul><li><a href='lib.rs.html#l8c10' target='_blank'>lib.rs: 8\n        </a></li><li><a href='lib.rs.html#l17c10' target='_blank'>lib.rs: 17"))));
+    assert!(lib.contains("title='Implements Shape'"));
+}
+
+#[test]
+fn outline_links_to_impl_headers() {
+    let (root, _) = gen_files("satire_gen_impl_headers", &[
+        ("src/lib.rs", "struct Unit;\n\nimpl Unit {\n}\n\nimpl Clone for Unit {\n    fn clone(&self) -> Unit {\n        Unit\n    }\n}\n"),
+    ], |_| {});
+
+    let lib = read(root.join("out/lib.rs.html"));
+    // Empty inherent impls are shown too
+    assert!(lib.contains("<li><span class='kind'>impl</span> <a href='#l3c6'>Unit</a></li>\
+        <li><span class='kind'>impl</span> <a href='#l6c6'>Clone for Unit</a><ul class='outline'>"));
+    assert!(lib.contains("<b>impl</b> <a name=\"l3c6\"></a><a tabindex='0'"));
+    assert!(lib.contains("title='Implementation of Unit'"));
+    assert!(lib.contains("<b>impl</b> <a name=\"l6c6\"></a><a tabindex='0'"));
+}
+
+#[test]
+fn lines_have_numbered_anchors() {
+    let (root, _) = gen_tree("satire_gen_lines", None);
+
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(lib.contains("<pre class=\"code\"><code><a class=\"line\" name=\"l1\" href=\"#l1\" data-line=\"1\"></a>"));
+    assert!(lib.contains("\n<a class=\"line\" name=\"l5\" href=\"#l5\" data-line=\"5\"></a>}"));
+}
+
+#[test]
+fn derives_link_to_traits() {
+    let (root, _) = gen_files("satire_gen_derive", &[
+        ("src/lib.rs", "pub trait Shape {\n}\n\n#[derive(Debug,\n    Shape)]\nstruct Square<'a> {\n    side: &'a u32,\n}\n\nfn derive() {\n}\n"),
+    ], |_| {});
+
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(lib.contains("<span class='attribute'>#[derive(Debug,\n\
+        <a class=\"line\" name=\"l5\" href=\"#l5\" data-line=\"5\"></a>    <a href='lib.rs.html#l1c11'>Shape</a>)]</span>"));
+    assert!(lib.contains("<span class='lifetime'>'a</span>"));
+    // `derive(` of the attribute isn't a call of `fn derive`, so it has no usages
+    assert!(lib.contains("<b>fn</b> <a name=\"l10c4\"></a><span class='fn def'>derive</span>()"));
+}
+
+#[test]
+fn doc_comments_are_shown_in_popovers_and_outline() {
+    let (root, _) = gen_files("satire_gen_docs", &[
+        ("src/lib.rs", "/// Side of the square.\n///\n/// Details.\npub fn side() -> u32 {\n    1\n}\n\n/** Area of\n * the \"square\". */\n#[inline]\nfn area() -> u32 {\n    side() * side()\n}\n\nstruct Point {\n    /// Not documenting `origin`\n    x: u32,\n}\n\nfn origin() {\n}\n"),
+    ], |_| {});
+
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(lib.contains("<span class='doc-comment'>/// Side of the square.</span>"));
+    assert!(lib.contains("<a href='#l4c8' title='Side of the square.'>side</a>"));
+    assert!(lib.contains("<a href='#l11c4' title='Area of the &quot;square&quot;.'>area</a>"));
+    assert!(lib.contains("<a href='#l20c4'>origin</a>"));
+    assert!(lib.contains(&format!("data-content=\"{}", escape_attr("<p class='doc'>Side of the square.</p><ul>"))));
+    assert!(lib.contains(&format!("data-content=\"{}\"", escape_attr("<p class='doc'>Area of the \"square\".</p>"))));
+}
+
+#[test]
+fn inner_doc_comments_document_modules() {
+    let (root, _) = gen_files("satire_gen_inner_docs", &[
+        ("src/lib.rs", "//! The crate.\n//!\n//! Details.\nmod shapes;\n\nmod colors {\n    //! Red and green.\n    pub fn red() {\n    }\n}\n\nuse shapes::{self, Square};\n"),
+        ("src/shapes.rs", "/*! Shapes of the crate. */\npub struct Square;\n"),
+    ], |_| {});
+
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(lib.contains("<p class='doc'>The crate.</p><ul class='outline'>"));
+    assert!(lib.contains("<a href='#l6c5' title='Red and green.'>colors</a>"));
+    assert!(lib.contains(&escape_attr("<p class='doc'>Shapes of the crate.</p><ul><li><a href='lib.rs.html#l4c5'")));
+
+    let shapes = read(root.join("out/shapes.rs.html"));
+    assert!(shapes.contains("<p class='doc'>Shapes of the crate.</p><ul class='outline'>"));
+}
+
+#[test]
+fn ampersands_are_escaped() {
+    let (root, _) = gen_files("satire_gen_ampersands", &[
+        ("src/lib.rs", "/// Black & white.\nfn both(a: bool) -> bool {\n    a && both(\"&amp;\" == \"\")\n}\n"),
+    ], |_| {});
+
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(lib.contains("a &amp;&amp; "));
+    assert!(lib.contains("\"&amp;amp;\""));
+    assert!(lib.contains("<a href='#l2c4' title='Black &amp; white.'>both</a>"));
+    assert!(lib.contains(&format!("data-content=\"{}", escape_attr("<p class='doc'>Black &amp; white.</p>"))));
+}
+
+#[test]
+fn macro_calls_link_to_macro_rules() {
+    let (root, _) = gen_files("satire_gen_macros", &[
+        ("src/lib.rs", "/// Squares the value.\nmacro_rules! square {\n    ($x:expr) => ($x * $x)\n}\n\nfn side() -> u32 {\n    2\n}\n\nfn area() -> u32 {\n    println!(\"{}\", side());\n    square!(side())\n}\n"),
+    ], |_| {});
+
+    let lib = read(root.join("out/lib.rs.html"));
+    // `println` isn't defined in the crate, `square` links to its `macro_rules!`
+    assert!(lib.contains("<a name=\"l11c5\"></a><span class='macro'>println</span>!(<span class='string'>\"{}\"</span>, <a name=\"l11c20\"></a><a tabindex='0'"));
+    assert!(lib.contains(&format!("<a name=\"l12c5\"></a><a tabindex='0' role='button' data-container='body' data-trigger='focus' \
+        data-toggle='popover' data-placement='bottom' data-content=\"{}", escape_attr("<p class='doc'>Squares the value.</p><ul><li><a href='lib.rs.html#l2c14'"))));
+    assert!(lib.contains("><span class='macro'>square</span></a>!(<a name=\"l12c13\"></a>"));
+    assert!(lib.contains("title='Usages (1)'"));
+    assert!(lib.contains("title='Usages (2)'"));
+}
+
+#[test]
+fn operators_are_highlighted_on_demand() {
+    let files = [("src/lib.rs", "fn len() -> usize {\n    1\n}\n\nfn range() {\n    for i in 0..len() {\n        let _ = i + 1 >= 2;\n    }\n}\n\nfn first(v: &Vec<u8>) -> u8 {\n    println!(\"{}\", !v.is_empty());\n    if v.len() < 2 && *v.first().unwrap() > 1 { 1 } else { 0 }\n}\n")];
+
+    let (root, _) = gen_files("satire_gen_no_operators", &files, |_| {});
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(!lib.contains("class='op'"));
+    // `..` isn't a method call
+    assert!(lib.contains("<span class='number'>0</span>..<a name=\"l6c17\"></a><a tabindex='0'"));
+
+    let (root, _) = gen_files("satire_gen_operators", &files, |config| config.operators = true);
+
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(lib.contains("<b>fn</b> <a name=\"l1c4\"></a>"));
+    assert!(lib.contains("() <span class='op'>-&gt;</span> "));
+    assert!(lib.contains("<span class='number'>0</span><span class='op'>..</span>"));
+    assert!(lib.contains("i <span class='op'>+</span> <span class='number'>1</span> <span class='op'>&gt;=</span>"));
+    // but not brackets of generics, `!` of macros or `&` of types
+    assert!(lib.contains("(v: &amp;Vec&lt;u8&gt;) <span class='op'>-&gt;</span>"));
+    assert!(lib.contains("</span>!(<span class='string'>\"{}\"</span>, <span class='op'>!</span>v."));
+    assert!(lib.contains("() <span class='op'>&lt;</span> <span class='number'>2</span> <span class='op'>&amp;&amp;</span> <span class='op'>*</span>v."));
+    assert!(lib.contains("() <span class='op'>&gt;</span> <span class='number'>1</span>"));
+}
+
+#[test]
+fn identifiers_are_highlighted_by_role() {
+    let (root, _) = gen_files("satire_gen_roles", &[
+        ("src/lib.rs", "const MAX: u32 = 3;\n\nenum Color {\n    Red,\n}\n\nstruct Point {\n    x: u32,\n}\n\nfn make(p: Point) -> Point {\n    // copy\n    let mut q = Point { x: MAX };\n    q.x = p.x;\n    q.clone();\n    undefined();\n    q\n}\n"),
+    ], |_| {});
+
+    let lib = read(root.join("out/lib.rs.html"));
+    assert!(lib.contains("<span class='constant def'>MAX</span>"));
+    assert!(lib.contains("<span class='type def'>Color</span>"));
+    assert!(lib.contains("<span class='variant def'>Red</span>"));
+    assert!(lib.contains("<span class='fn def'>make</span>"));
+    // Types of arguments and results link to their definitions
+    assert!(lib.contains("(p: <a name=\"l11c12\"></a><a tabindex='0'"));
+    assert!(lib.contains(&escape_attr("<li><a href='lib.rs.html#l7c8' target='_blank'>lib.rs: 7")));
+    assert!(lib.contains("\"><span class='type'>Point</span></a>) -&gt; "));
+    // but only the struct literal is a usage
+    assert!(lib.contains(&format!("title='Usages (1)' data-content=\"{}", escape_attr("<ul><li>lib.rs: <a href='lib.rs.html#l13c17'"))));
+    assert!(lib.contains("<span class='comment'>// copy</span>"));
+    assert!(lib.contains("<b>mut</b> <span class='local'>q</span>"));
+    assert!(lib.contains("x: MAX }"));
+    assert!(lib.contains("q.<span class='field'>x</span> = p.<span class='field'>x</span>;"));
+    assert!(lib.contains("q.<a name=\"l15c7\"></a><span class='method'>clone</span>();"));
+    assert!(lib.contains("<a name=\"l16c5\"></a><span class='fn'>undefined</span>();"));
+}
+
+#[test]
+fn updates_regenerate_affected_pages() {
+    let (root, config) = files_config("satire_gen_update", &[
+        ("template.html", "{{content}}"),
+        ("src/lib.rs", "mod shapes;\n\nfn area() {\n    shapes::square();\n}\n"),
+        ("src/shapes.rs", "pub fn square() {\n}\n"),
+        ("src/colors.rs", "pub fn red() {\n}\n"),
+        ("src/notes.rs", "pub fn note() {\n}\n"),
+        ("src/extra/more.rs", "pub fn more() {\n}\n"),
+    ], |_| {});
+
+    let mut index_builder = IndexBuilder::with_config(config);
+    let update = |index_builder: &mut IndexBuilder| -> (Vec<String>, Vec<String>, Vec<String>, Vec<String>) {
+        // Edits can keep the modification time on coarse file systems, contents decide then
+        for parsed_file in index_builder.set.iter_mut() {
+            parsed_file.mtime = 0;
+        }
+        let source_root = index_builder.config.source_root.clone();
+        let mut report = index_builder.update_dir(&source_root).unwrap();
+        index_builder.gen_affected(&mut report).unwrap();
+
+        let relative = |files: &Vec<String>| -> Vec<String> {
+            let mut files: Vec<String> = files.iter().map(|file| file[source_root.len()..].to_string()).collect();
+            files.sort();
+            files
+        };
+        (relative(&report.reparsed), relative(&report.reused), relative(&report.removed), relative(report.regenerated.as_ref().unwrap()))
+    };
+
+    let (reparsed, _, _, regenerated) = update(&mut index_builder);
+    assert_eq!(reparsed, vec!["colors.rs", "extra/more.rs", "lib.rs", "notes.rs", "shapes.rs"]);
+    assert_eq!(regenerated, reparsed);
+    // Pages which are written again lose the mark
+    let mark = |page: &str| File::create(root.join("out").join(page)).unwrap().write_all(b"mark").unwrap();
+    mark("index.html");
+    mark("extra/index.html");
+
+    // Only the edited file and the ones using its names are regenerated
+    File::create(root.join("src/shapes.rs")).unwrap().write_all(b"pub fn square() {\n}\n\npub fn circle() {\n}\n").unwrap();
+    let (reparsed, reused, removed, regenerated) = update(&mut index_builder);
+    assert_eq!(reparsed, vec!["shapes.rs"]);
+    assert_eq!(reused, vec!["colors.rs", "extra/more.rs", "lib.rs", "notes.rs"]);
+    assert_eq!(removed, Vec::<String>::new());
+    assert_eq!(regenerated, vec!["lib.rs", "shapes.rs"]);
+    assert!(read(root.join("out/shapes.rs.html")).contains("circle"));
+    // Directories without changed files keep their pages
+    assert!(read(root.join("out/index.html")) != "mark");
+    assert_eq!(read(root.join("out/extra/index.html")), "mark");
+
+    // Removed files take their pages with them, every page is regenerated
+    fs::remove_file(root.join("src/colors.rs")).unwrap();
+    File::create(root.join("src/sizes.rs")).unwrap().write_all(b"pub fn big() {\n}\n").unwrap();
+    let (reparsed, reused, removed, regenerated) = update(&mut index_builder);
+    assert_eq!(reparsed, vec!["sizes.rs"]);
+    assert_eq!(reused, vec!["extra/more.rs", "lib.rs", "notes.rs", "shapes.rs"]);
+    assert_eq!(removed, vec!["colors.rs"]);
+    assert_eq!(regenerated, vec!["extra/more.rs", "lib.rs", "notes.rs", "shapes.rs", "sizes.rs"]);
+    assert!(read(root.join("out/extra/index.html")) != "mark");
+    assert!(!root.join("out/colors.rs.html").exists());
+    assert!(root.join("out/sizes.rs.html").exists());
+    assert!(!read(root.join("out/tree-index.js")).contains("colors.rs"));
+}
+
+/// Contents of all files under `dir` by their paths relative to it.
+fn read_all(dir: &PathBuf, prefix: &str, all: &mut Vec<(String, String)>) {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+    entries.sort();
+    for entry in entries {
+        let name = format!("{}{}", prefix, entry.file_name().unwrap().to_str().unwrap());
+        if entry.is_dir() {
+            read_all(&entry, &format!("{}/", name), all);
+        } else {
+            all.push((name, read(entry)));
+        }
+    }
+}
+
+#[test]
+fn threads_dont_change_output() {
+    let root = write_tree("satire_gen_jobs", &[
+        ("template.html", "<title>{{title}}</title>{{tree}}{{content}}{{outline}}"),
+    ]);
+    // The parsers of satire are big enough for the threads to interleave
+    let source_root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/indexer/lang");
+
+    let mut outputs = vec![];
+    for &jobs in &[1, 4] {
+        let output = root.join(format!("out{}", jobs));
+        let mut config = GenConfig::new(source_root.to_str().unwrap(), output.to_str().unwrap());
+        config.template = root.join("template.html").to_str().unwrap().to_string();
+
+        let mut index_builder = IndexBuilder::with_config(config.clone());
+        index_builder.jobs = jobs;
+        index_builder.build_dir(&config.source_root).unwrap();
+        index_builder.gen().unwrap();
+
+        let mut all = vec![];
+        read_all(&output, "", &mut all);
+        outputs.push(all);
+    }
+
+    assert!(outputs[0].len() > 5);
+    let names = |all: &Vec<(String, String)>| -> Vec<String> { all.iter().map(|&(ref name, _)| name.clone()).collect() };
+    assert_eq!(names(&outputs[0]), names(&outputs[1]));
+    for (single, parallel) in outputs[0].iter().zip(&outputs[1]) {
+        assert!(single.1 == parallel.1, "{} differs", single.0);
+    }
+}
@@ -0,0 +1,65 @@
+extern crate satire;
+
+use std::sync::Arc;
+
+use satire::indexer::lang::rust::RustParser;
+use satire::indexer::parser::{CommonParser, Tagged, ImportContext};
+
+fn imports(content: &str) -> Vec<ImportContext> {
+    let preparsed = RustParser::new("test.rs".to_string(), Arc::new(content.to_string())).parse();
+    preparsed.parsed.into_iter().filter_map(|(tagged, _)| {
+        match tagged {
+            Tagged::Import(import) => Some(import),
+            _ => None,
+        }
+    }).collect()
+}
+
+/// Imports as `path [as alias] [in module]`.
+fn show(import: &ImportContext) -> String {
+    let mut shown = import.path.join("::");
+    if let Some(ref alias) = import.alias {
+        shown.push_str(&format!(" as {}", alias));
+    }
+    if import.used_from.path.len() > 0 {
+        shown.push_str(&format!(" in {}", import.used_from));
+    }
+    shown
+}
+
+#[test]
+fn use_trees() {
+    let table = [
+        ("use a::b;", vec!["a", "a::b as b"]),
+        ("use a::b as c;", vec!["a", "a::b as c"]),
+        ("use a::{b, c::{self, d as e}, f::*};", vec!["a", "a::b as b", "a::c as c", "a::c::d as e", "a::f", "a::f::*"]),
+        ("use ::a::b;", vec!["a", "a::b as b"]),
+        ("use crate::a;", vec!["crate::a as a"]),
+        ("mod m {\n    use super::a;\n    use self::b::c as d;\n}", vec!["super::a as a in m", "self::b in m", "self::b::c as d in m"]),
+    ];
+
+    for &(content, ref expected) in table.iter() {
+        let shown: Vec<String> = imports(content).iter().map(show).collect();
+        assert_eq!(&shown, expected, "{}", content);
+    }
+}
+
+#[test]
+fn absolute_paths() {
+    let module = vec!["x".to_string(), "y".to_string()];
+    let table = [
+        ("use a::b;", vec!["a", "a::b"]),
+        ("use self::a;", vec!["x::y::a"]),
+        ("use super::a;", vec!["x::a"]),
+        ("use super::super::a;", vec!["a"]),
+        ("use crate::a;", vec!["a"]),
+        // Globs are paths of the modules themselves
+        ("use self::a::*;", vec!["x::y::a", "x::y::a"]),
+        ("mod m {\n    use super::a;\n}", vec!["x::y::a"]),
+    ];
+
+    for &(content, ref expected) in table.iter() {
+        let absolute: Vec<String> = imports(content).iter().map(|import| import.absolute(&module).join("::")).collect();
+        assert_eq!(&absolute, expected, "{}", content);
+    }
+}
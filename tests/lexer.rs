@@ -0,0 +1,202 @@
+extern crate satire;
+
+use satire::indexer::lexer::{Token, Span, WhitespaceType};
+use satire::indexer::lang::rust::RustLexer;
+
+/// Lexems of `content` till `Token::Eof` without spaces.
+fn lex(content: &str) -> Vec<(Token, Span)> {
+    let mut lexems = vec![];
+    for (token, span) in RustLexer::new(content) {
+        match token {
+            Token::Eof => { break; },
+            Token::Whitespace(WhitespaceType::Spaces) => {},
+            _ => { lexems.push((token, span)); },
+        }
+    }
+    lexems
+}
+
+fn idents(content: &str) -> Vec<String> {
+    lex(content).into_iter().filter_map(|(token, _)| {
+        match token {
+            Token::Ident(name) => Some(name),
+            _ => None,
+        }
+    }).collect()
+}
+
+#[test]
+fn non_ascii_identifiers() {
+    assert_eq!(idents("fn größe(дом: u8) {}"), vec!["größe", "дом", "u8"]);
+    assert_eq!(idents("let _東京 = café;"), vec!["_東京", "café"]);
+    assert_eq!(idents("fnü(); ü_1"), vec!["fnü", "ü_1"]);
+}
+
+#[test]
+fn keywords_are_not_extended_by_punctuation() {
+    let lexems = lex("fn é()");
+    match lexems[1].0 {
+        Token::T_fn => {},
+        ref token => panic!("fn expected, got {:?}", token),
+    }
+}
+
+#[test]
+fn multibyte_columns() {
+    let content = "let s = \"日本\"; x\n// Комментарий\nfn 𝔸() {}";
+    let lexems = lex(content);
+
+    let string = lexems.iter().find(|&&(ref token, _)| match token { &Token::QuotedString => true, _ => false }).unwrap().1;
+    assert_eq!(&content[string.lo..string.hi], "\"日本\"");
+    assert_eq!((string.line, string.col, string.col_utf16), (1, 9, 9));
+    assert_eq!((string.end_line, string.end_col, string.end_col_utf16), (1, 17, 13));
+
+    let (_, x) = lexems.iter().find(|&&(ref token, _)| match token { &Token::Ident(ref name) => name == "x", _ => false }).unwrap().clone();
+    assert_eq!((x.line, x.col, x.col_utf16), (1, 19, 15));
+
+    let (_, comment) = lexems.iter().find(|&&(ref token, _)| match token { &Token::Comment => true, _ => false }).unwrap().clone();
+    assert_eq!(&content[comment.lo..comment.hi], "// Комментарий");
+    assert_eq!((comment.line, comment.end_col, comment.end_col_utf16), (2, 26, 15));
+
+    let (_, ident) = lexems.iter().find(|&&(ref token, _)| match token { &Token::Ident(ref name) => name == "𝔸", _ => false }).unwrap().clone();
+    assert_eq!((ident.line, ident.col, ident.end_col, ident.end_col_utf16), (3, 4, 8, 6));
+}
+
+#[test]
+fn lines_after_multiline_comments() {
+    let lexems = lex("/* один\nдва */ fn f() {}\nfn g() {}");
+    let lines: Vec<(String, usize)> = lexems.into_iter().filter_map(|(token, span)| {
+        match token {
+            Token::Ident(name) => Some((name, span.line)),
+            _ => None,
+        }
+    }).collect();
+    assert_eq!(lines, vec![("f".to_string(), 2), ("g".to_string(), 3)]);
+}
+
+#[test]
+fn byte_order_mark_is_skipped() {
+    let content = "\u{feff}fn f() {}";
+    let lexems = lex(content);
+
+    assert_eq!(lexems[0].1.lo, 3);
+    match lexems[1].0 {
+        Token::T_fn => {},
+        ref token => panic!("fn expected, got {:?}", token),
+    }
+    assert_eq!((lexems[1].1.lo, lexems[1].1.col, lexems[1].1.col_utf16), (3, 1, 1));
+}
+
+/// Texts of lexems except newlines, spaces and the end.
+fn texts(content: &str) -> Vec<(String, String)> {
+    lex(content).into_iter().filter_map(|(token, span)| {
+        match token {
+            Token::Whitespace(_) => None,
+            _ => Some((format!("{:?}", token), content[span.lo..span.hi].to_string())),
+        }
+    }).collect()
+}
+
+fn literal(content: &str) -> (String, String) {
+    let lexems = texts(content);
+    assert_eq!(lexems.len(), 1, "{:?}", lexems);
+    lexems.into_iter().next().unwrap()
+}
+
+#[test]
+fn string_literals() {
+    assert_eq!(literal(r#""a \"quoted\" \\ string""#).0, "QuotedString");
+    assert_eq!(literal("\"first\nsecond\"").0, "QuotedString");
+    assert_eq!(literal(r#"b"bytes\x7f""#).0, "ByteString");
+    assert_eq!(literal(r##"r#"raw "quoted" string"#"##).0, "RawString");
+    assert_eq!(literal(r#"r"C:\path""#).0, "RawString");
+    assert_eq!(literal(r###"br##"raw "# bytes"##"###).0, "RawByteString");
+}
+
+#[test]
+fn raw_string_ends_at_matching_hashes() {
+    let lexems = texts(r###"let s = r##"a "# b"##; r"###);
+    assert_eq!(lexems[3], ("RawString".to_string(), r###"r##"a "# b"##"###.to_string()));
+    assert_eq!(lexems[5], ("Ident(\"r\")".to_string(), "r".to_string()));
+}
+
+#[test]
+fn char_literals() {
+    assert_eq!(literal("'x'").0, "Char");
+    assert_eq!(literal("'\\''").0, "Char");
+    assert_eq!(literal("'\\\\'").0, "Char");
+    assert_eq!(literal("'\\u{1F600}'").0, "Char");
+    assert_eq!(literal("'я'").0, "Char");
+    assert_eq!(literal("b'\\n'").0, "Byte");
+}
+
+#[test]
+fn number_literals() {
+    for number in &["0", "42", "1_000_000", "0xFF_u8", "0o777", "0b1010_1010", "1e-3", "2.5E10", "3.14f32", "7usize"] {
+        assert_eq!(literal(number), ("Number".to_string(), number.to_string()));
+    }
+
+    let lexems = texts("0..10");
+    assert_eq!(lexems.first().unwrap().1, "0");
+    assert_eq!(lexems.last().unwrap().1, "10");
+}
+
+#[test]
+fn literals_keep_lines() {
+    let lexems = lex("let s = \"a\nb\";\nfn f() {}");
+    let f = lexems.iter().find(|&&(ref token, _)| match token { &Token::Ident(ref name) => name == "f", _ => false }).unwrap();
+    assert_eq!(f.1.line, 3);
+}
+
+#[test]
+fn lifetimes_and_labels() {
+    assert_eq!(texts("fn f<'a>(x: &'a str, c: char) { 'outer: loop { break 'outer; } }")
+        .into_iter().filter(|&(ref token, _)| token == "Lifetime").map(|(_, text)| text).collect::<Vec<_>>(),
+        vec!["'a", "'a", "'outer", "'outer"]);
+    assert_eq!(literal("'a'").0, "Char");
+    assert_eq!(literal("'static").0, "Lifetime");
+    assert_eq!(literal("'ä").0, "Lifetime");
+    assert_eq!(literal("'ä'").0, "Char");
+    assert_eq!(texts("fn f<'aé, 'дом>(x: &'aé str) {}")
+        .into_iter().filter(|&(ref token, _)| token == "Lifetime").map(|(_, text)| text).collect::<Vec<_>>(),
+        vec!["'aé", "'дом", "'aé"]);
+}
+
+#[test]
+fn nested_block_comments() {
+    let lexems = texts("a /* x /* y */ z */ b /* open /* */");
+    assert_eq!(lexems, vec![
+        ("Ident(\"a\")".to_string(), "a".to_string()),
+        ("Comment".to_string(), "/* x /* y */ z */".to_string()),
+        ("Ident(\"b\")".to_string(), "b".to_string()),
+        ("Comment".to_string(), "/* open /* */".to_string()),
+    ]);
+}
+
+#[test]
+fn doc_comments() {
+    let tokens = |content| texts(content).into_iter().map(|(token, _)| token).collect::<Vec<_>>();
+    assert_eq!(tokens("/// Outer\n//! Inner\n// Plain\n//// Plain"), vec!["DocComment", "InnerDocComment", "Comment", "Comment"]);
+    assert_eq!(tokens("/** Outer */ /*! Inner */ /* Plain */ /*** Plain */ /**/"),
+        vec!["DocComment", "InnerDocComment", "Comment", "Comment", "Comment"]);
+}
+
+#[test]
+fn macro_punctuation() {
+    assert_eq!(texts("vec![a != b]").into_iter().map(|(token, _)| token).collect::<Vec<_>>(),
+        vec!["Ident(\"vec\")", "Bang", "LBracket", "Ident(\"a\")", "Ne", "Ident(\"b\")", "RBracket"]);
+}
+
+#[test]
+fn punctuation() {
+    let tokens = |content| texts(content).into_iter().map(|(token, _)| token).collect::<Vec<_>>();
+    assert_eq!(tokens("-> => .. ... ..= :: : ; , . ? @ $ ~ #"),
+        vec!["RArrow", "FatArrow", "DotDot", "DotDotDot", "DotDotEq", "Colon2", "Colon", "Semi", "Comma", "Dot",
+            "Question", "At", "Dollar", "Tilde", "Pound"]);
+    assert_eq!(tokens("= == != < <= > >= << >> <<= >>="),
+        vec!["Eq", "EqEq", "Ne", "Lt", "Le", "Gt", "Ge", "Shl", "Shr", "ShlEq", "ShrEq"]);
+    assert_eq!(tokens("+ - * / % ^ & | && || += -= *= /= %= ^= &= |="),
+        vec!["Plus", "Minus", "Star", "Slash", "Percent", "Caret", "And", "Or", "AndAnd", "OrOr",
+            "PlusEq", "MinusEq", "StarEq", "SlashEq", "PercentEq", "CaretEq", "AndEq", "OrEq"]);
+    assert_eq!(tokens("0..n.len()"), vec!["Number", "DotDot", "Ident(\"n\")", "Dot", "Ident(\"len\")", "LParen", "RParen"]);
+}
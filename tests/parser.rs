@@ -0,0 +1,80 @@
+extern crate satire;
+
+use std::sync::Arc;
+
+use satire::indexer::lang::rust::RustParser;
+use satire::indexer::parser::{CommonParser, Tagged};
+
+/// Parsed items of `content` with their texts.
+fn parse(content: &str) -> Vec<(String, Tagged)> {
+    let preparsed = RustParser::new("test.rs".to_string(), Arc::new(content.to_string())).parse();
+    preparsed.parsed.into_iter().map(|(tagged, span)| (content[span.lo..span.hi].to_string(), tagged)).collect()
+}
+
+#[test]
+fn matching_does_not_lag_after_failed_partial_matches() {
+    // `Token::A` isn't the start of `Token::new(...)`, the tokens of the failed match
+    // stayed in the cache and `len(` after the lagging `.items` was taken for a function call
+    let parsed = parse("fn f() {\n    match t {\n        Token::A | Token::B => {},\n    }\n    for i in 0..v.items.len() {}\n}\n");
+    let len = parsed.iter().find(|&&(ref text, _)| text == "len").unwrap();
+    match len.1 {
+        Tagged::MethodCall(ref context) => assert_eq!(context.reference.name(), "len"),
+        ref tagged => panic!("method call expected, got {:?}", tagged),
+    }
+
+    // `a::B;` isn't a call, the definition after it was tagged when its block was already open
+    let parsed = parse("use a::B;\npub enum C {\n}\n");
+    let c = parsed.iter().find(|&&(ref text, _)| text == "C").unwrap();
+    match c.1 {
+        Tagged::Definition(ref context) => {
+            assert_eq!(format!("{}", context.reference), "C");
+            assert_eq!(context.used_from.path.len(), 0);
+        },
+        ref tagged => panic!("definition expected, got {:?}", tagged),
+    }
+}
+
+#[test]
+fn types_are_not_calls() {
+    let parsed = parse("fn g<T: Clone, U: Iterator<Item = u8>>(t: T, u: U) -> Vec<T> {\n    let p: Point = t;\n}\n");
+
+    let types: Vec<&str> = parsed.iter().filter_map(|&(ref text, ref tagged)| {
+        match tagged {
+            &Tagged::TypeRef(_) => Some(&text[..]),
+            &Tagged::Calling(_) => panic!("{} isn't a call", text),
+            _ => None,
+        }
+    }).collect();
+    // Bounds in the generics aren't types of values
+    assert_eq!(types, vec!["T", "U", "Vec", "Point"]);
+}
+
+#[test]
+fn raw_pointers_are_not_definitions() {
+    let parsed = parse(include_str!("../test/pointers.rs"));
+
+    let definitions: Vec<&str> = parsed.iter().filter_map(|&(ref text, ref tagged)| {
+        match tagged {
+            &Tagged::Definition(_) => Some(&text[..]),
+            _ => None,
+        }
+    }).collect();
+    assert_eq!(definitions, vec!["ZERO", "strlen", "raw"]);
+}
+
+#[test]
+fn method_calls() {
+    let parsed = parse("fn f() {\n    x.draw();\n    x.shape.draw(1).fill();\n    Shape::draw(x);\n    x.draw;\n}\n");
+
+    let methods: Vec<(&str, usize)> = parsed.iter().filter_map(|&(ref text, ref tagged)| {
+        match tagged {
+            &Tagged::MethodCall(ref context) => {
+                assert_eq!(format!("{}", context.used_from), "f");
+                Some((&text[..], context.reference.path.len()))
+            },
+            _ => None,
+        }
+    }).collect();
+    // `Shape::draw` is a path and `x.draw` a field
+    assert_eq!(methods, vec![("draw", 1), ("draw", 1), ("fill", 1)]);
+}
@@ -0,0 +1,87 @@
+extern crate satire;
+
+mod common;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{Cursor, ErrorKind};
+use std::path::PathBuf;
+
+use satire::indexer::persist::{self, Persist, MAGIC, VERSION};
+use satire::indexer::storage::{IndexBuilder, DeducedFile, INDEX_FILE, content_hash};
+
+use common::write_tree;
+
+/// Everything deduced about the files, in a comparable form.
+fn show(deduced: &[DeducedFile]) -> Vec<String> {
+    deduced.iter().map(|deduced_file| format!("{}: {:?} {:?}", deduced_file.file, deduced_file.synt, deduced_file.pars)).collect()
+}
+
+/// Index of a small crate saved into `name/index`.
+fn saved(name: &str) -> (IndexBuilder, PathBuf) {
+    let root = write_tree(name, &[
+        ("src/lib.rs", "//! The crate.\n\nmod shapes;\n\nuse shapes::Square;\n\nfn area(square: &Square) -> u32 {\n    square.side() * shapes::unit()\n}\n"),
+        ("src/shapes.rs", "/// A square.\npub struct Square;\n\nimpl Square {\n    pub fn side(&self) -> u32 {\n        1\n    }\n}\n\npub fn unit() -> u32 {\n    1\n}\n"),
+    ]);
+
+    let mut index_builder = IndexBuilder::new();
+    index_builder.build_dir(root.join("src").to_str().unwrap()).unwrap();
+    let dir = root.join("index");
+    index_builder.save(dir.to_str().unwrap()).unwrap();
+    (index_builder, dir)
+}
+
+#[test]
+fn loaded_index_deduces_the_same() {
+    let (index_builder, dir) = saved("satire_persist_roundtrip");
+    let loaded = IndexBuilder::load(dir.to_str().unwrap()).unwrap();
+
+    assert_eq!(loaded.config.source_root, index_builder.config.source_root);
+    assert_eq!(loaded.set.len(), index_builder.set.len());
+    for (parsed_file, loaded_file) in index_builder.set.iter().zip(&loaded.set) {
+        assert_eq!(loaded_file.file, parsed_file.file);
+        assert_eq!(loaded_file.mtime, parsed_file.mtime);
+        assert_eq!(loaded_file.preparsed.module, parsed_file.preparsed.module);
+        assert_eq!(loaded_file.preparsed.hash, parsed_file.preparsed.hash);
+        assert_eq!(format!("{:?}", loaded_file.preparsed.docs), format!("{:?}", parsed_file.preparsed.docs));
+        assert_eq!(loaded_file.preparsed.module_doc, parsed_file.preparsed.module_doc);
+    }
+    // The symbol table is rebuilt, so references resolve as before saving
+    assert_eq!(show(&loaded.deduce()), show(&index_builder.deduce()));
+}
+
+#[test]
+fn other_versions_and_files_are_rejected() {
+    let (_, dir) = saved("satire_persist_header");
+    let index_file = dir.join(INDEX_FILE);
+    let mut saved = vec![];
+    File::open(&index_file).unwrap().read_to_end(&mut saved).unwrap();
+
+    let mut header = vec![];
+    persist::save_header(&mut header).unwrap();
+    assert!(saved.starts_with(&header));
+    assert!(persist::load_header(&mut Cursor::new(&header)).is_ok());
+
+    let mut older = MAGIC.to_vec();
+    (VERSION - 1).save(&mut older).unwrap();
+    let mut other = b"other-index!".to_vec();
+    VERSION.save(&mut other).unwrap();
+    for mut corrupted in vec![older, other] {
+        corrupted.extend_from_slice(&saved[header.len()..]);
+        File::create(&index_file).unwrap().write_all(&corrupted).unwrap();
+
+        match IndexBuilder::load(dir.to_str().unwrap()) {
+            Err(ref error) if error.kind() == ErrorKind::InvalidData => {},
+            Err(error) => panic!("invalid data expected, got {}", error),
+            Ok(_) => panic!("corrupted index was loaded"),
+        }
+    }
+}
+
+#[test]
+fn content_hashes_are_stable() {
+    // Saved in the index, so they must not depend on the compiler
+    assert_eq!(content_hash(""), 0xcbf29ce484222325);
+    assert_eq!(content_hash("a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(content_hash("foobar"), 0x85944171f73967e8);
+}
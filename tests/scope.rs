@@ -0,0 +1,82 @@
+extern crate satire;
+
+use std::sync::Arc;
+
+use satire::indexer::lang::rust::RustParser;
+use satire::indexer::lexer::Token;
+use satire::indexer::parser::{CommonParser, Path, Tagged};
+
+fn parse(content: &str) -> Vec<Tagged> {
+    let preparsed = RustParser::new("test.rs".to_string(), Arc::new(content.to_string())).parse();
+    preparsed.parsed.into_iter().map(|(tagged, _)| tagged).collect()
+}
+
+/// Paths of the definitions with their kinds, e.g. `struct Foo::fn new`.
+fn definitions(content: &str) -> Vec<String> {
+    parse(content).into_iter().filter_map(|tagged| {
+        match tagged {
+            Tagged::Definition(context) => Some(context.reference.path.iter()
+                .map(|&(ref token, ref name)| format!("{} {}", Path::named(token.clone(), String::new()).kind(), name))
+                .collect::<Vec<String>>().join("::")),
+            _ => None,
+        }
+    }).collect()
+}
+
+fn path(items: &[(Token, &str)]) -> Path {
+    Path::path(items.iter().map(|&(ref token, name)| (token.clone(), name.to_string())).collect())
+}
+
+#[test]
+fn methods_are_in_their_types() {
+    assert_eq!(definitions("struct Foo;\n\nimpl Foo {\n    fn new() -> Foo {\n        Foo\n    }\n}\n\nfn free() {\n}\n"),
+        vec!["struct Foo", "struct Foo::fn new", "fn free"]);
+}
+
+#[test]
+fn nested_modules() {
+    assert_eq!(definitions("mod a {\n    fn b() {\n    }\n\n    mod c {\n        fn d() {\n        }\n    }\n}\n\nfn e() {\n}\n"),
+        vec!["mod a", "mod a::fn b", "mod a::mod c", "mod a::mod c::fn d", "fn e"]);
+}
+
+#[test]
+fn leading_modules_and_functions_can_be_omitted() {
+    let nested = path(&[(Token::T_mod, "a"), (Token::T_fn, "b"), (Token::T_fn, "c")]);
+    assert!(nested.matches(&path(&[(Token::T_fn, "c")])));
+    assert!(nested.matches(&path(&[(Token::T_fn, "b"), (Token::T_fn, "c")])));
+    assert!(nested.matches(&nested));
+    assert!(!nested.matches(&path(&[(Token::T_mod, "a"), (Token::T_fn, "c")])));
+    assert!(!nested.matches(&path(&[(Token::T_fn, "b")])));
+
+    // Methods are referred with their types
+    let method = path(&[(Token::T_mod, "a"), (Token::T_struct, "Foo"), (Token::T_fn, "new")]);
+    assert!(method.matches(&path(&[(Token::T_struct, "Foo"), (Token::T_fn, "new")])));
+    assert!(!method.matches(&path(&[(Token::T_fn, "new")])));
+    // Types and functions have different namespaces
+    assert!(!method.matches(&path(&[(Token::T_fn, "Foo"), (Token::T_fn, "new")])));
+}
+
+/// Trait implementations as `trait for self type: items`.
+fn implementations(content: &str) -> Vec<String> {
+    parse(content).into_iter().filter_map(|tagged| {
+        match tagged {
+            Tagged::Implementation(ref context) if !context.is_inherent() => {
+                let items: Vec<String> = context.items.iter().map(|&(ref item, _)| item.clone()).collect();
+                Some(format!("{} for {}: {}", context.trait_ref, context.self_type, items.join(", ")))
+            },
+            _ => None,
+        }
+    }).collect()
+}
+
+#[test]
+fn trait_implementations_in_modules() {
+    assert_eq!(implementations("mod shapes {\n    struct Square;\n\n    impl Shape for Square {\n        fn area(&self) -> u32 {\n            1\n        }\n\n        fn side(&self) -> u32 {\n            1\n        }\n    }\n}\n"),
+        vec!["Shape for shapes::Square: area, side"]);
+}
+
+#[test]
+fn generic_implementation_headers() {
+    assert_eq!(implementations("impl<'a> X<'a> for Y {\n}\n\nimpl<T: Clone> parser::Show<T> for Vec<T> where T: Debug {\n    fn show(&self) {\n    }\n}\n\nimpl<'a, T> From<&'a [T]> for Wrapper<'a, T> {\n}\n"),
+        vec!["X for Y: ", "Show for Vec: show", "From for Wrapper: "]);
+}
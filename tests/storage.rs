@@ -0,0 +1,118 @@
+extern crate satire;
+
+mod common;
+
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+
+use satire::indexer::lexer::Token;
+use satire::indexer::parser::Path;
+use satire::indexer::storage::{IndexBuilder, INDEX_FILE};
+
+use common::write_tree;
+
+/// Index of `files` written into `name`.
+fn build(name: &str, files: &[(&str, &str)]) -> IndexBuilder {
+    let root = write_tree(name, files);
+    let mut index_builder = IndexBuilder::new();
+    index_builder.build_dir(root.to_str().unwrap()).unwrap();
+    index_builder
+}
+
+const SHAPE: &'static str = "pub struct Shape;\n\nimpl Shape {\n    pub fn draw(&self) {\n    }\n}\n";
+
+#[test]
+fn method_candidates_are_ranked_by_module() {
+    // `c.rs` is in the directory of `a.rs`, but `a/b.rs` is in its module
+    let index_builder = build("satire_storage_methods", &[
+        ("lib.rs", "mod a;\nmod c;\n"),
+        ("a.rs", "mod b;\n\nfn f(x: X) {\n    x.draw();\n}\n"),
+        ("a/b.rs", SHAPE),
+        ("c.rs", SHAPE),
+        ("d.rs", "struct Own;\n\nimpl Own {\n    fn draw(&self) {\n    }\n}\n"),
+    ]);
+    let index = index_builder.index();
+
+    let files = |module: &[&str], file: &str| -> Vec<String> {
+        let module: Vec<String> = module.iter().map(|name| name.to_string()).collect();
+        index.methods("draw", file, &module).into_iter().map(|(_, source)| source.file).collect()
+    };
+    assert_eq!(files(&["a"], "a.rs"), vec!["a/b.rs", "c.rs", "d.rs"]);
+    assert_eq!(files(&["c"], "c.rs"), vec!["c.rs", "a/b.rs", "d.rs"]);
+    // Methods of the same file go first whatever the module is
+    assert_eq!(files(&["a"], "d.rs")[0], "d.rs");
+}
+
+#[test]
+fn references_follow_path_matching() {
+    let index_builder = build("satire_storage_references", &[
+        ("lib.rs", "mod bar {\n    pub fn foo() {\n    }\n}\n\nstruct Foo;\n\nimpl Foo {\n    fn new() -> Foo {\n        Foo\n    }\n}\n\nfn main() {\n    bar::foo();\n    Foo::new();\n}\n"),
+    ]);
+    let index = index_builder.index();
+
+    let lines = |path: Path| -> Vec<usize> { index.references(&path).into_iter().map(|usage| usage.line).collect() };
+    assert_eq!(lines(Path::path(vec![(Token::T_mod, "bar".to_string()), (Token::T_fn, "foo".to_string())])), vec![15]);
+    // Leading modules can be omitted, but not the types of methods
+    assert_eq!(lines(Path::named(Token::T_fn, "foo".to_string())), vec![15]);
+    assert_eq!(lines(Path::path(vec![(Token::T_struct, "Foo".to_string()), (Token::T_fn, "new".to_string())])), vec![16]);
+    assert_eq!(lines(Path::named(Token::T_fn, "new".to_string())), Vec::<usize>::new());
+}
+
+#[test]
+fn modules_of_files() {
+    let index_builder = build("satire_storage_modules", &[
+        ("lib.rs", "mod foo;\nmod bar;\nmod both;\n"),
+        ("foo.rs", "mod inner;\n"),
+        ("foo/inner.rs", ""),
+        ("bar/mod.rs", "pub mod deep;\n"),
+        ("bar/deep.rs", ""),
+        // `both.rs` is taken before `both/mod.rs`
+        ("both.rs", ""),
+        ("both/mod.rs", ""),
+        ("orphan/file.rs", ""),
+    ]);
+
+    let mut modules: Vec<(String, String)> = index_builder.set.iter()
+        .map(|parsed_file| (parsed_file.preparsed.file.clone(), parsed_file.preparsed.module.join("::")))
+        .collect();
+    modules.sort();
+    assert_eq!(modules, vec![
+        ("bar/deep.rs".to_string(), "bar::deep".to_string()),
+        ("bar/mod.rs".to_string(), "bar".to_string()),
+        ("both.rs".to_string(), "both".to_string()),
+        // Not reachable from `lib.rs`, the module is guessed from the path
+        ("both/mod.rs".to_string(), "both".to_string()),
+        ("foo.rs".to_string(), "foo".to_string()),
+        ("foo/inner.rs".to_string(), "foo::inner".to_string()),
+        ("lib.rs".to_string(), "".to_string()),
+        ("orphan/file.rs".to_string(), "orphan::file".to_string()),
+    ]);
+}
+
+#[test]
+fn missing_roots_leave_the_index_unchanged() {
+    let mut index_builder = build("satire_storage_missing_root", &[
+        ("lib.rs", "mod shapes;\n"),
+        ("shapes.rs", SHAPE),
+    ]);
+    let root = index_builder.config.source_root.clone();
+    let saved = |index_builder: &IndexBuilder, dir: &str| -> Vec<u8> {
+        let dir = env::temp_dir().join("satire_storage_missing_root_index").join(dir);
+        index_builder.save(dir.to_str().unwrap()).unwrap();
+        let mut saved = vec![];
+        File::open(dir.join(INDEX_FILE)).unwrap().read_to_end(&mut saved).unwrap();
+        saved
+    };
+    let before = saved(&index_builder, "before");
+
+    // A mistyped directory isn't a directory with all files removed
+    assert!(index_builder.update_dir(&format!("{}-missing", &root[..root.len() - 1])).is_err());
+    assert_eq!(index_builder.config.source_root, root);
+    assert_eq!(index_builder.set.len(), 2);
+    assert_eq!(index_builder.previous.len(), 0);
+    assert_eq!(saved(&index_builder, "after"), before);
+
+    let report = index_builder.update_dir(&root).unwrap();
+    assert_eq!((report.reparsed.len(), report.reused.len(), report.removed.len()), (0, 2, 0));
+}
@@ -0,0 +1,80 @@
+// Highlighting of the line or the range of lines in the URL fragment: #l10 or #l10-l25.
+// Links to identifiers (#l10c5) highlight their lines.
+// Shift-click on a line number extends the highlighted line to the range.
+
+// [from, to] of the fragment or null.
+function parseLines(hash) {
+    var match = /^#l(\d+)(?:c\d+)?(?:-l?(\d+)(?:c\d+)?)?$/.exec(hash);
+    if (!match) {
+        return null;
+    }
+
+    var from = parseInt(match[1], 10);
+    var to = match[2] ? parseInt(match[2], 10) : from;
+    return from <= to ? [from, to] : [to, from];
+}
+
+function formatLines(from, to) {
+    return from === to ? '#l' + from : '#l' + from + '-l' + to;
+}
+
+$(function() {
+    var code = $('pre.code');
+    if (!code.length) {
+        return;
+    }
+
+    function anchor(line) {
+        return code.find('a.line[name="l' + line + '"]');
+    }
+
+    function highlight(scroll) {
+        code.find('.line-highlight').remove();
+
+        var lines = parseLines(window.location.hash);
+        if (!lines) {
+            return;
+        }
+
+        var first = anchor(lines[0]);
+        if (!first.length) {
+            return;
+        }
+        var next = anchor(lines[1] + 1);
+
+        var top = first.position().top;
+        var bottom = next.length ? next.position().top : code.innerHeight();
+        code.append($('<div class="line-highlight">').css({top: top, height: bottom - top}));
+
+        if (scroll) {
+            var target = code.find('a[name="' + window.location.hash.substring(1) + '"]');
+            $(window).scrollTop((target.length ? target : first).offset().top - $(window).height() / 4);
+        }
+    }
+
+    code.on('click', 'a.line', function(e) {
+        var line = parseInt($(this).data('line'), 10);
+        var current = parseLines(window.location.hash);
+
+        var hash = formatLines(line, line);
+        if (e.shiftKey && current) {
+            hash = formatLines(Math.min(current[0], line), Math.max(current[0], line));
+        }
+
+        e.preventDefault();
+        if (window.history && window.history.replaceState) {
+            window.history.replaceState(null, '', hash);
+        } else {
+            window.location.hash = hash;
+        }
+        highlight(false);
+    });
+
+    $(window).on('hashchange', function() {
+        highlight(true);
+    });
+    $(window).on('load', function() {
+        highlight(true);
+    });
+    highlight(true);
+});
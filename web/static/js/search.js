@@ -0,0 +1,117 @@
+// Symbol search over SEARCH_INDEX of search-index.js generated next to the pages.
+// Entries are [name, kind, path, file, line, col].
+
+var MAX_RESULTS = 50;
+
+// Lower is better, -1 if the query doesn't match:
+// exact name, then name prefix, then substring, then fuzzy (letters in order).
+function matchScore(text, query) {
+    if (text === query) {
+        return 0;
+    }
+    if (text.indexOf(query) === 0) {
+        return 1;
+    }
+    if (text.indexOf(query) > 0) {
+        return 2;
+    }
+
+    var gaps = 0;
+    var last = -1;
+    for (var i = 0; i < query.length; i++) {
+        var found = text.indexOf(query[i], last + 1);
+        if (found < 0) {
+            return -1;
+        }
+        if (last >= 0) {
+            gaps += found - last - 1;
+        }
+        last = found;
+    }
+    return 3 + gaps;
+}
+
+function searchSymbols(entries, query) {
+    query = query.toLowerCase();
+    // Qualified queries like `Foo::new` are matched against full paths
+    var byPath = query.indexOf('::') >= 0;
+
+    var found = [];
+    for (var i = 0; i < entries.length; i++) {
+        var entry = entries[i];
+        var text = (byPath ? entry[2] : entry[0]).toLowerCase();
+        var score = matchScore(text, query);
+        if (byPath && score > 2) {
+            // Paths end with the name, so a suffix is as good as a prefix
+            var suffix = text.lastIndexOf(query);
+            if (suffix >= 0 && suffix + query.length === text.length) {
+                score = 1;
+            }
+        }
+        if (score >= 0) {
+            found.push([score, entry]);
+        }
+    }
+
+    found.sort(function(a, b) {
+        return a[0] - b[0] || a[1][0].length - b[1][0].length || (a[1][2] < b[1][2] ? -1 : a[1][2] > b[1][2] ? 1 : 0);
+    });
+    return found.slice(0, MAX_RESULTS).map(function(item) { return item[1]; });
+}
+
+$(function() {
+    var input = $('#search');
+    var results = $('#search-results');
+    if (!input.length) {
+        return;
+    }
+
+    var root = input.data('root') || '';
+    var entries = typeof SEARCH_INDEX !== 'undefined' ? SEARCH_INDEX : [];
+    var selected = 0;
+
+    function url(entry) {
+        return root + entry[3] + '.html#l' + entry[4] + 'c' + entry[5];
+    }
+
+    function render() {
+        results.empty();
+        var query = $.trim(input.val());
+        if (!query) {
+            return;
+        }
+
+        var found = searchSymbols(entries, query);
+        selected = Math.min(selected, found.length - 1);
+        $.each(found, function(i, entry) {
+            var item = $('<li>').toggleClass('selected', i === selected);
+            var link = $('<a>').attr('href', url(entry));
+            link.append($('<span class="kind">').text(entry[1] + ' '));
+            link.append($('<b>').text(entry[2]));
+            link.append($('<span class="place">').text(' ' + entry[3] + ':' + entry[4]));
+            results.append(item.append(link));
+        });
+    }
+
+    input.on('input', function() {
+        selected = 0;
+        render();
+    });
+
+    input.on('keydown', function(e) {
+        var items = results.children();
+        if (e.which === 40 || e.which === 38) {
+            selected = (selected + (e.which === 40 ? 1 : items.length - 1)) % Math.max(items.length, 1);
+            items.removeClass('selected').eq(selected).addClass('selected');
+            e.preventDefault();
+        } else if (e.which === 13) {
+            var link = items.eq(selected).find('a');
+            if (link.length) {
+                window.location.href = link.attr('href');
+            }
+        } else if (e.which === 27) {
+            input.val('');
+            render();
+        }
+    });
+});
@@ -0,0 +1,37 @@
+// Sidebar of the page from SOURCE_TREE of tree-index.js, which is generated once for all pages.
+// Links of the tree are from the output root, `data-root` is the output root from the page.
+// Directories on the way to `data-current` are expanded, the current item is highlighted.
+
+function showTree(container, tree) {
+    var root = container.getAttribute('data-root');
+    var current = container.getAttribute('data-current');
+    container.innerHTML = tree;
+
+    var links = container.getElementsByTagName('a');
+    for (var i = 0; i < links.length; i++) {
+        var href = links[i].getAttribute('href');
+        // Absolute with the base URL
+        if (!/^([a-z]+:|\/)/i.test(href)) {
+            links[i].setAttribute('href', root + href);
+        }
+    }
+
+    var items = container.querySelectorAll('[data-path]');
+    for (var j = 0; j < items.length; j++) {
+        var path = items[j].getAttribute('data-path');
+        if (path === current) {
+            items[j].className = 'current';
+        }
+        var summary = items[j].parentNode;
+        if (summary.tagName === 'SUMMARY' && current.indexOf(path) === 0) {
+            summary.parentNode.open = true;
+        }
+    }
+}
+
+(function() {
+    var container = document.getElementById('tree');
+    if (container && typeof SOURCE_TREE !== 'undefined') {
+        showTree(container, SOURCE_TREE);
+    }
+})();